@@ -0,0 +1,8 @@
+use identicon::server::{router, Config};
+use shuttle_runtime::CustomError;
+
+#[shuttle_runtime::main]
+async fn main() -> shuttle_axum::ShuttleAxum {
+    let config = Config::load().map_err(CustomError::msg)?;
+    Ok(router(config).into())
+}
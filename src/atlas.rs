@@ -0,0 +1,212 @@
+use std::{fmt, thread};
+
+use image::RgbaImage;
+use serde::Serialize;
+
+use crate::IdenticonOptions;
+
+/// Where one input was placed in an [`Atlas`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AtlasEntry {
+    pub name: String,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Many identicons packed row by row into a single sprite sheet.
+#[derive(Debug, Clone)]
+pub struct Atlas {
+    pub image: RgbaImage,
+    pub entries: Vec<AtlasEntry>,
+}
+
+/// The sheet for an [`Atlas`] has sides that overflow a `u32`, or its buffer
+/// could not be allocated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasTooLarge {
+    pub columns: usize,
+    pub rows: usize,
+    pub tile: u32,
+}
+
+impl fmt::Display for AtlasTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "a {}x{} atlas of {}px tiles is too large",
+            self.columns, self.rows, self.tile
+        )
+    }
+}
+
+impl std::error::Error for AtlasTooLarge {}
+
+impl Atlas {
+    /// Packs `names` into a sheet that is as close to square as possible.
+    pub fn new<S: AsRef<str> + Sync>(
+        options: &IdenticonOptions,
+        names: &[S],
+    ) -> Result<Self, AtlasTooLarge> {
+        let columns = (names.len() as f64).sqrt().ceil() as usize;
+        Self::with_columns(options, names, columns)
+    }
+
+    /// Packs `names` into a sheet `columns` tiles wide. Rows of tiles are
+    /// rendered in parallel; cells past the last name stay transparent.
+    pub fn with_columns<S: AsRef<str> + Sync>(
+        options: &IdenticonOptions,
+        names: &[S],
+        columns: usize,
+    ) -> Result<Self, AtlasTooLarge> {
+        let tile = options.size;
+        let columns = columns.clamp(1, names.len().max(1));
+        let rows = names.len().div_ceil(columns);
+        let too_large = AtlasTooLarge {
+            columns,
+            rows,
+            tile,
+        };
+        let side = |tiles: usize| {
+            u32::try_from(tiles)
+                .ok()
+                .and_then(|tiles| tiles.checked_mul(tile))
+        };
+        let (Some(width), Some(height)) = (side(columns), side(rows)) else {
+            return Err(too_large);
+        };
+        let band_len = (width as usize)
+            .checked_mul(tile as usize)
+            .and_then(|pixels| pixels.checked_mul(4))
+            .ok_or(too_large)?;
+        let len = band_len
+            .checked_mul(rows)
+            .filter(|&len| len <= isize::MAX as usize)
+            .ok_or(too_large)?;
+
+        let entries = names
+            .iter()
+            .enumerate()
+            .map(|(ix, name)| AtlasEntry {
+                name: name.as_ref().to_string(),
+                x: (ix % columns) as u32 * tile,
+                y: (ix / columns) as u32 * tile,
+                width: tile,
+                height: tile,
+            })
+            .collect();
+
+        let mut buf = Vec::new();
+        buf.try_reserve_exact(len).map_err(|_| too_large)?;
+        buf.resize(len, 0);
+        let mut bands: Vec<_> = buf.chunks_mut(band_len.max(1)).enumerate().collect();
+        let render = |group: &mut [(usize, &mut [u8])]| {
+            for (row, band) in group {
+                let first = *row * columns;
+                let last = names.len().min(first + columns);
+                for (col, name) in names[first..last].iter().enumerate() {
+                    let image = options.gen_rgba(name.as_ref().as_bytes());
+                    blit(band, width, col as u32 * tile, &image);
+                }
+            }
+        };
+        // without threads, as on wasm, spawning would panic
+        let workers = thread::available_parallelism().map_or(1, |n| n.get());
+        if workers == 1 {
+            render(&mut bands);
+        } else {
+            let per_worker = bands.len().div_ceil(workers).max(1);
+            thread::scope(|s| {
+                for group in bands.chunks_mut(per_worker) {
+                    s.spawn(|| render(group));
+                }
+            });
+        }
+
+        Ok(Atlas {
+            image: RgbaImage::from_raw(width, height, buf).unwrap(),
+            entries,
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.entries).unwrap()
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("name,x,y,width,height\n");
+        for entry in &self.entries {
+            let name = if entry.name.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", entry.name.replace('"', "\"\""))
+            } else {
+                entry.name.clone()
+            };
+            csv += &format!(
+                "{},{},{},{},{}\n",
+                name, entry.x, entry.y, entry.width, entry.height
+            );
+        }
+        csv
+    }
+}
+
+/// Copies `tile` into a band of rows `width` pixels wide, starting at column `x`.
+fn blit(band: &mut [u8], width: u32, x: u32, tile: &RgbaImage) {
+    let stride = tile.width() as usize * 4;
+    for (y, row) in tile.as_raw().chunks(stride).enumerate() {
+        let start = (y * width as usize + x as usize) * 4;
+        band[start..start + stride].copy_from_slice(row);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Atlas;
+    use crate::{IdenticonOptions, TRANSPARENT};
+
+    #[test]
+    fn it_packs_tiles() {
+        let options = IdenticonOptions::new().size(24);
+        let names = ["a", "b", "c", "d", "e"];
+        let atlas = Atlas::with_columns(&options, &names, 3).unwrap();
+        assert_eq!(atlas.image.dimensions(), (72, 48));
+
+        for (entry, name) in atlas.entries.iter().zip(names) {
+            let tile = options.gen_rgba(name.as_bytes());
+            for (x, y, pixel) in tile.enumerate_pixels() {
+                assert_eq!(atlas.image.get_pixel(entry.x + x, entry.y + y), pixel);
+            }
+        }
+        assert_eq!(*atlas.image.get_pixel(71, 47), TRANSPARENT);
+    }
+
+    #[test]
+    fn it_rejects_oversized_sheets() {
+        let options = IdenticonOptions::new().size(4096);
+        let names = vec!["a"; 1 << 20];
+        let err = Atlas::with_columns(&options, &names, names.len()).unwrap_err();
+        assert_eq!((err.columns, err.rows), (1 << 20, 1));
+    }
+
+    #[test]
+    fn it_rejects_sheets_it_cannot_allocate() {
+        // 4 EiB fits in an isize but not in any address space
+        let options = IdenticonOptions::new().size(1 << 30);
+        let err = Atlas::new(&options, &["a"]).unwrap_err();
+        assert_eq!(err.tile, 1 << 30);
+    }
+
+    #[test]
+    fn it_writes_indexes() {
+        let options = IdenticonOptions::new().size(8);
+        let atlas = Atlas::new(&options, &["a", "b,\"c\""]).unwrap();
+        assert_eq!(
+            atlas.to_csv(),
+            "name,x,y,width,height\na,0,0,8,8\n\"b,\"\"c\"\"\",8,0,8,8\n"
+        );
+        let json: serde_json::Value = serde_json::from_str(&atlas.to_json()).unwrap();
+        assert_eq!(json[1]["name"], "b,\"c\"");
+        assert_eq!(json[1]["x"], 8);
+    }
+}
@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+use identicon::{
+    utils, Atlas, CellShape, ColorMode, Format, GraphicsProtocol, HashAlgorithm, Hsl,
+    IdenticonOptions, Mask, Palette, Symmetry, TerminalMode,
+};
+use image::{Rgb, Rgba};
+
+#[derive(Debug, Clone, Copy)]
+enum Print {
+    Text(TerminalMode),
+    Image(GraphicsProtocol),
+}
+
+/// Render identicons for names given as arguments or on stdin.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Args {
+    /// Names to render. Read one per line from stdin when none are given, or
+    /// when one of them is `-`.
+    names: Vec<String>,
+
+    /// Directory the images are written to.
+    #[arg(short, long, default_value = ".")]
+    out_dir: PathBuf,
+
+    /// png, svg, webp, avif, jpg, gif or ico.
+    #[arg(short, long, default_value = "png")]
+    format: Format,
+
+    /// Width and height in pixels.
+    #[arg(short, long, default_value_t = 290, value_parser = clap::value_parser!(u32).range(1..=4096))]
+    size: u32,
+
+    /// Border around the sprite in pixels. Defaults to half a cell.
+    #[arg(long)]
+    margin: Option<u32>,
+
+    /// Cells along each side of the sprite.
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..=64))]
+    grid: u32,
+
+    /// horizontal, vertical, four-way, rotational-180, rotational-90 or none.
+    #[arg(long)]
+    symmetry: Option<Symmetry>,
+
+    /// dark, pastel, material, accessible or grayscale.
+    #[arg(long)]
+    palette: Option<Palette>,
+
+    /// Comma-separated hex colors to use as a custom palette.
+    #[arg(long, value_delimiter = ',', conflicts_with = "palette", value_parser = parse_foreground)]
+    colors: Vec<Rgb<u8>>,
+
+    /// Derive colors from the hash in HSL space instead of a palette.
+    #[arg(long, conflicts_with_all = ["palette", "colors"])]
+    hsl: bool,
+
+    /// HSL saturation range in percent, as `MIN-MAX`.
+    #[arg(long, requires = "hsl", value_parser = parse_percent_range)]
+    saturation: Option<(u8, u8)>,
+
+    /// HSL lightness range in percent, as `MIN-MAX`.
+    #[arg(long, requires = "hsl", value_parser = parse_percent_range)]
+    lightness: Option<(u8, u8)>,
+
+    /// WCAG contrast ratio HSL colors keep against the background.
+    #[arg(long, requires = "hsl")]
+    min_contrast: Option<f32>,
+
+    /// mono, two-tone, three-tone or accent.
+    #[arg(long)]
+    color_mode: Option<ColorMode>,
+
+    /// `transparent` or a hex color.
+    #[arg(long, value_parser = parse_background)]
+    bg: Option<Rgba<u8>>,
+
+    /// square, rounded, circle, triangle or diamond.
+    #[arg(long)]
+    shape: Option<CellShape>,
+
+    /// square, rounded, circle or squircle.
+    #[arg(long)]
+    mask: Option<Mask>,
+
+    /// md5, sha256, blake3 or xxh3.
+    #[arg(long)]
+    hash: Option<HashAlgorithm>,
+
+    /// Smooth edges by supersampling.
+    #[arg(long)]
+    antialias: bool,
+
+    /// Print to the terminal instead of writing files. Text art with
+    /// truecolor, 256 or ascii, or an image with sixel, kitty or iterm2.
+    #[arg(long, conflicts_with = "atlas", value_parser = parse_print)]
+    print: Option<Print>,
+
+    /// Pack every image into one sprite sheet named `<ATLAS>.<format>`
+    /// instead of writing a file per name.
+    #[arg(long)]
+    atlas: Option<String>,
+
+    /// Tiles per atlas row. Defaults to a roughly square sheet.
+    #[arg(long, requires = "atlas", value_parser = clap::value_parser!(u32).range(1..))]
+    columns: Option<u32>,
+
+    /// Format of the index written next to the atlas.
+    #[arg(long, requires = "atlas", default_value = "json", value_parser = ["json", "csv"])]
+    index: String,
+}
+
+impl Args {
+    fn options(&self) -> IdenticonOptions {
+        let mut options = IdenticonOptions::new()
+            .size(self.size)
+            .grid(self.grid)
+            .antialias(self.antialias);
+        if let Some(margin) = self.margin {
+            options = options.margin(margin);
+        }
+        if let Some(symmetry) = self.symmetry {
+            options = options.symmetry(symmetry);
+        }
+        if let Some(palette) = &self.palette {
+            options = options.palette(palette.clone());
+        }
+        if !self.colors.is_empty() {
+            options = options.palette(Palette::new(self.colors.clone()));
+        }
+        if self.hsl {
+            let mut hsl = Hsl::new();
+            if let Some((min, max)) = self.saturation {
+                hsl = hsl.saturation(min, max);
+            }
+            if let Some((min, max)) = self.lightness {
+                hsl = hsl.lightness(min, max);
+            }
+            if let Some(ratio) = self.min_contrast {
+                hsl = hsl.min_contrast(ratio);
+            }
+            options = options.colors(hsl);
+        }
+        if let Some(color_mode) = self.color_mode {
+            options = options.color_mode(color_mode);
+        }
+        if let Some(bg) = self.bg {
+            options = options.background(bg);
+        }
+        if let Some(shape) = self.shape {
+            options = options.shape(shape);
+        }
+        if let Some(mask) = self.mask {
+            options = options.mask(mask);
+        }
+        if let Some(hash) = self.hash {
+            options = options.hash(hash);
+        }
+        options
+    }
+
+    fn names(&self) -> io::Result<Vec<String>> {
+        if !self.names.is_empty() && !self.names.iter().any(|name| name == "-") {
+            return Ok(self.names.clone());
+        }
+
+        let mut names: Vec<_> = self.names.iter().filter(|n| *n != "-").cloned().collect();
+        for line in io::stdin().lock().lines() {
+            let line = line?;
+            if !line.is_empty() {
+                names.push(line);
+            }
+        }
+        Ok(names)
+    }
+}
+
+/// Keeps names usable as file names on any platform.
+fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '_',
+        })
+        .collect();
+    match stem.trim_start_matches('.') {
+        "" => "_".to_string(),
+        _ => stem,
+    }
+}
+
+fn parse_percent_range(s: &str) -> Result<(u8, u8), String> {
+    let (min, max) = s.split_once('-').ok_or("expected `MIN-MAX`")?;
+    let percent = |p: &str| match p.trim().parse::<u8>() {
+        Ok(p) if p <= 100 => Ok(p),
+        _ => Err(format!("`{}` is not a percentage", p)),
+    };
+    Ok((percent(min)?, percent(max)?))
+}
+
+fn parse_foreground(s: &str) -> Result<Rgb<u8>, String> {
+    match utils::parse_color(s) {
+        Some(Rgba([r, g, b, 255])) => Ok(Rgb([r, g, b])),
+        _ => Err("expected an opaque hex color".to_string()),
+    }
+}
+
+fn parse_background(s: &str) -> Result<Rgba<u8>, String> {
+    utils::parse_color(s).ok_or_else(|| "expected `transparent` or a hex color".to_string())
+}
+
+fn parse_print(s: &str) -> Result<Print, String> {
+    TerminalMode::from_name(s)
+        .map(Print::Text)
+        .or_else(|| GraphicsProtocol::from_name(s).map(Print::Image))
+        .ok_or_else(|| format!("unknown terminal output `{}`", s))
+}
+
+fn write_atlas(args: &Args, options: &IdenticonOptions, stem: &str) -> io::Result<()> {
+    let names = args.names()?;
+    let atlas = match args.columns {
+        Some(columns) => Atlas::with_columns(options, &names, columns as usize),
+        None => Atlas::new(options, &names),
+    }
+    .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let index = match args.index.as_str() {
+        "csv" => atlas.to_csv(),
+        _ => atlas.to_json(),
+    };
+    let Some(image) = args.format.encode_rgba(atlas.image) else {
+        let msg = format!("atlases can't be written as {}", args.format.extension());
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+    };
+
+    let stem = file_stem(stem);
+    for (ext, bytes) in [
+        (args.format.extension(), image),
+        (&args.index, index.into_bytes()),
+    ] {
+        let path = args.out_dir.join(format!("{}.{}", stem, ext));
+        fs::write(&path, bytes)?;
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+/// Fails before writing anything if two different names would end up in
+/// the same file.
+fn check_collisions(names: &[String], format: Format) -> io::Result<()> {
+    let mut seen = HashMap::new();
+    for name in names {
+        // case-insensitive file systems would overwrite one with the other
+        if let Some(other) = seen.insert(file_stem(name).to_ascii_lowercase(), name) {
+            if other != name {
+                let msg = format!(
+                    "`{}` and `{}` would both be written to `{}.{}`",
+                    other,
+                    name,
+                    file_stem(name),
+                    format.extension()
+                );
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run(args: &Args) -> io::Result<()> {
+    let options = args.options();
+
+    if args.print.is_none() {
+        fs::create_dir_all(&args.out_dir)?;
+    }
+    if let Some(atlas) = &args.atlas {
+        return write_atlas(args, &options, atlas);
+    }
+    let names = args.names()?;
+    if args.print.is_none() {
+        check_collisions(&names, args.format)?;
+    }
+    for name in names {
+        match args.print {
+            Some(Print::Text(mode)) => print!("{}", options.gen_terminal(name.as_bytes(), mode)),
+            Some(Print::Image(protocol)) => {
+                println!("{}", options.gen_graphics(name.as_bytes(), protocol))
+            }
+            None => {
+                let path =
+                    args.out_dir
+                        .join(format!("{}.{}", file_stem(&name), args.format.extension()));
+                fs::write(&path, args.format.encode(&options, name.as_bytes()))?;
+                println!("{}", path.display());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(&Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use identicon::Format;
+
+    use super::{check_collisions, file_stem, parse_percent_range};
+
+    #[test]
+    fn it_sanitizes_file_stems() {
+        assert_eq!(file_stem("alice"), "alice");
+        assert_eq!(file_stem("a/b c"), "a_b_c");
+        assert_eq!(file_stem("../etc"), ".._etc");
+        assert_eq!(file_stem(".."), "_");
+    }
+
+    #[test]
+    fn it_detects_collisions() {
+        let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        assert!(check_collisions(&names(&["a", "b", "a"]), Format::Png).is_ok());
+        assert!(check_collisions(&names(&["a/b", "a_b"]), Format::Png).is_err());
+        assert!(check_collisions(&names(&["Alice", "alice"]), Format::Png).is_err());
+    }
+
+    #[test]
+    fn it_parses_percent_ranges() {
+        assert_eq!(parse_percent_range("40-80"), Ok((40, 80)));
+        assert_eq!(parse_percent_range(" 0 - 100 "), Ok((0, 100)));
+        assert!(parse_percent_range("40").is_err());
+        assert!(parse_percent_range("40-101").is_err());
+    }
+}
@@ -0,0 +1,13 @@
+#[cfg(feature = "image")]
+pub use image::{Rgb, Rgba};
+
+/// An RGB pixel. Without the `image` feature this stands in for
+/// `image::Rgb`, with the same shape so code builds either way.
+#[cfg(not(feature = "image"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Rgb<T>(pub [T; 3]);
+
+/// An RGBA pixel, standing in for `image::Rgba`.
+#[cfg(not(feature = "image"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Rgba<T>(pub [T; 4]);
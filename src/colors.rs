@@ -1,6 +1,210 @@
-use image::Rgb;
+use std::borrow::Cow;
+use std::str::FromStr;
 
-pub(crate) const DARK_COLORS: [Rgb<u8>; 124] = [
+use crate::utils::UnknownName;
+use crate::{Hsl, Rgb, Rgba};
+
+/// How the foreground color is derived from the hash.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ColorStrategy {
+    /// Picks one of a fixed list of colors.
+    Palette(Palette),
+    /// Computes a color from continuous hue, saturation and lightness.
+    Hsl(Hsl),
+}
+
+impl ColorStrategy {
+    /// Picks `count` colors; the first is always the one a single-color
+    /// identicon would get.
+    pub(crate) fn pick(&self, hash: &[u8], count: usize, background: Rgba<u8>) -> Vec<Rgb<u8>> {
+        match self {
+            ColorStrategy::Palette(palette) => palette.pick(hash, count),
+            ColorStrategy::Hsl(hsl) => (0..count)
+                .map(|n| hsl.pick(hash, background, n as f64 * 360.0 / count as f64))
+                .collect(),
+        }
+    }
+}
+
+/// How many colors the painted cells are split between.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ColorMode {
+    /// Every painted cell uses the foreground color.
+    #[default]
+    Mono,
+    /// Painted cells are split evenly between two colors.
+    TwoTone,
+    /// Painted cells are split evenly between three colors.
+    ThreeTone,
+    /// About a quarter of the painted cells use a second, accent color.
+    Accent,
+}
+
+impl ColorMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "mono" => Some(ColorMode::Mono),
+            "two-tone" => Some(ColorMode::TwoTone),
+            "three-tone" => Some(ColorMode::ThreeTone),
+            "accent" => Some(ColorMode::Accent),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn colors(self) -> usize {
+        match self {
+            ColorMode::Mono => 1,
+            ColorMode::TwoTone | ColorMode::Accent => 2,
+            ColorMode::ThreeTone => 3,
+        }
+    }
+
+    /// Maps a nibble of the tone stream to an index into the picked colors.
+    pub(crate) fn tone(self, nibble: u8) -> u8 {
+        match self {
+            ColorMode::Mono => 0,
+            ColorMode::TwoTone => nibble % 2,
+            ColorMode::ThreeTone => nibble % 3,
+            ColorMode::Accent => (nibble < 4) as u8,
+        }
+    }
+}
+
+impl FromStr for ColorMode {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ColorMode::from_name(s).ok_or_else(|| UnknownName::new("color mode", s))
+    }
+}
+
+impl Default for ColorStrategy {
+    fn default() -> Self {
+        ColorStrategy::Palette(Palette::DARK)
+    }
+}
+
+impl From<Palette> for ColorStrategy {
+    fn from(palette: Palette) -> Self {
+        ColorStrategy::Palette(palette)
+    }
+}
+
+impl From<Hsl> for ColorStrategy {
+    fn from(hsl: Hsl) -> Self {
+        ColorStrategy::Hsl(hsl)
+    }
+}
+
+/// A list of foreground colors that identicons pick from by hash.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Palette {
+    colors: Cow<'static, [Rgb<u8>]>,
+}
+
+impl Palette {
+    /// The original 124 dark web-safe colors.
+    pub const DARK: Palette = Palette::from_static(&DARK_COLORS);
+    /// Soft, light tints for dark or saturated backgrounds.
+    pub const PASTEL: Palette = Palette::from_static(&PASTEL_COLORS);
+    /// The Material Design 500 shades.
+    pub const MATERIAL: Palette = Palette::from_static(&MATERIAL_COLORS);
+    /// Dark Material shades with at least 4.5:1 contrast against the default
+    /// background, per WCAG AA.
+    pub const ACCESSIBLE: Palette = Palette::from_static(&ACCESSIBLE_COLORS);
+    pub const GRAYSCALE: Palette = Palette::from_static(&GRAYSCALE_COLORS);
+
+    const fn from_static(colors: &'static [Rgb<u8>]) -> Self {
+        Palette {
+            colors: Cow::Borrowed(colors),
+        }
+    }
+
+    /// A custom palette.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `colors` is empty.
+    pub fn new(colors: impl Into<Vec<Rgb<u8>>>) -> Self {
+        let colors = colors.into();
+        assert!(!colors.is_empty(), "a palette needs at least one color");
+        Palette {
+            colors: Cow::Owned(colors),
+        }
+    }
+
+    /// Looks up a built-in palette by its lowercase name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Palette::DARK),
+            "pastel" => Some(Palette::PASTEL),
+            "material" => Some(Palette::MATERIAL),
+            "accessible" => Some(Palette::ACCESSIBLE),
+            "grayscale" => Some(Palette::GRAYSCALE),
+            _ => None,
+        }
+    }
+
+    pub fn colors(&self) -> &[Rgb<u8>] {
+        &self.colors
+    }
+
+    pub(crate) fn pick(&self, hash: &[u8], count: usize) -> Vec<Rgb<u8>> {
+        let len = self.colors.len();
+        let first = (hash[11] as usize + hash[12] as usize + hash[15] as usize) % len;
+        let stride = 1 + hash[14] as usize % len.saturating_sub(1).max(1);
+
+        let mut picked = vec![first];
+        let mut ix = first;
+        while picked.len() < count.min(len) {
+            ix = (ix + stride) % len;
+            // a stride sharing a factor with `len` cycles early; step past it
+            while picked.contains(&ix) {
+                ix = (ix + 1) % len;
+            }
+            picked.push(ix);
+        }
+        // palettes smaller than `count` reuse their colors
+        (0..count)
+            .map(|n| self.colors[picked[n % picked.len()]])
+            .collect()
+    }
+}
+
+impl FromStr for Palette {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Palette::from_name(s).ok_or_else(|| UnknownName::new("palette", s))
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::DARK
+    }
+}
+
+/// WCAG relative luminance.
+pub(crate) fn luminance(Rgb(rgb): Rgb<u8>) -> f64 {
+    let [r, g, b] = rgb.map(|c| {
+        let c = c as f64 / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    });
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+/// WCAG contrast ratio, from 1 to 21.
+pub(crate) fn contrast(a: Rgb<u8>, b: Rgb<u8>) -> f64 {
+    let (a, b) = (luminance(a), luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+const DARK_COLORS: [Rgb<u8>; 124] = [
     Rgb([0x00, 0x00, 0x33]),
     Rgb([0x00, 0x00, 0x66]),
     Rgb([0x00, 0x00, 0x99]),
@@ -126,3 +330,102 @@ pub(crate) const DARK_COLORS: [Rgb<u8>; 124] = [
     Rgb([0xcc, 0xcc, 0x99]),
     Rgb([0xcc, 0xcc, 0xcc]),
 ];
+
+const PASTEL_COLORS: [Rgb<u8>; 16] = [
+    Rgb([0xff, 0xad, 0xad]),
+    Rgb([0xff, 0xd6, 0xa5]),
+    Rgb([0xfd, 0xff, 0xb6]),
+    Rgb([0xca, 0xff, 0xbf]),
+    Rgb([0x9b, 0xf6, 0xff]),
+    Rgb([0xa0, 0xc4, 0xff]),
+    Rgb([0xbd, 0xb2, 0xff]),
+    Rgb([0xff, 0xc6, 0xff]),
+    Rgb([0xb5, 0xea, 0xd7]),
+    Rgb([0xc7, 0xce, 0xea]),
+    Rgb([0xe2, 0xf0, 0xcb]),
+    Rgb([0xff, 0xda, 0xc1]),
+    Rgb([0xff, 0x9a, 0xa2]),
+    Rgb([0xf3, 0xb0, 0xc3]),
+    Rgb([0xc6, 0xde, 0xf1]),
+    Rgb([0xdb, 0xcd, 0xf0]),
+];
+
+const MATERIAL_COLORS: [Rgb<u8>; 19] = [
+    Rgb([0xf4, 0x43, 0x36]),
+    Rgb([0xe9, 0x1e, 0x63]),
+    Rgb([0x9c, 0x27, 0xb0]),
+    Rgb([0x67, 0x3a, 0xb7]),
+    Rgb([0x3f, 0x51, 0xb5]),
+    Rgb([0x21, 0x96, 0xf3]),
+    Rgb([0x03, 0xa9, 0xf4]),
+    Rgb([0x00, 0xbc, 0xd4]),
+    Rgb([0x00, 0x96, 0x88]),
+    Rgb([0x4c, 0xaf, 0x50]),
+    Rgb([0x8b, 0xc3, 0x4a]),
+    Rgb([0xcd, 0xdc, 0x39]),
+    Rgb([0xff, 0xeb, 0x3b]),
+    Rgb([0xff, 0xc1, 0x07]),
+    Rgb([0xff, 0x98, 0x00]),
+    Rgb([0xff, 0x57, 0x22]),
+    Rgb([0x79, 0x55, 0x48]),
+    Rgb([0x9e, 0x9e, 0x9e]),
+    Rgb([0x60, 0x7d, 0x8b]),
+];
+
+const ACCESSIBLE_COLORS: [Rgb<u8>; 22] = [
+    Rgb([0xc6, 0x28, 0x28]),
+    Rgb([0xad, 0x14, 0x57]),
+    Rgb([0x6a, 0x1b, 0x9a]),
+    Rgb([0x45, 0x27, 0xa0]),
+    Rgb([0x28, 0x35, 0x93]),
+    Rgb([0x15, 0x65, 0xc0]),
+    Rgb([0x01, 0x57, 0x9b]),
+    Rgb([0x00, 0x60, 0x64]),
+    Rgb([0x00, 0x69, 0x5c]),
+    Rgb([0x33, 0x69, 0x1e]),
+    Rgb([0xbf, 0x36, 0x0c]),
+    Rgb([0x4e, 0x34, 0x2e]),
+    Rgb([0x37, 0x47, 0x4f]),
+    Rgb([0xb7, 0x1c, 0x1c]),
+    Rgb([0x88, 0x0e, 0x4f]),
+    Rgb([0x4a, 0x14, 0x8c]),
+    Rgb([0x31, 0x1b, 0x92]),
+    Rgb([0x1a, 0x23, 0x7e]),
+    Rgb([0x0d, 0x47, 0xa1]),
+    Rgb([0x00, 0x4d, 0x40]),
+    Rgb([0x1b, 0x5e, 0x20]),
+    Rgb([0x5d, 0x40, 0x37]),
+];
+
+const GRAYSCALE_COLORS: [Rgb<u8>; 8] = [
+    Rgb([0x20, 0x20, 0x20]),
+    Rgb([0x30, 0x30, 0x30]),
+    Rgb([0x40, 0x40, 0x40]),
+    Rgb([0x50, 0x50, 0x50]),
+    Rgb([0x60, 0x60, 0x60]),
+    Rgb([0x70, 0x70, 0x70]),
+    Rgb([0x80, 0x80, 0x80]),
+    Rgb([0x90, 0x90, 0x90]),
+];
+
+#[cfg(test)]
+mod tests {
+    use crate::Rgb;
+
+    use super::{contrast, Palette};
+
+    #[test]
+    fn it_keeps_accessible_palette_above_aa_contrast() {
+        for color in Palette::ACCESSIBLE.colors() {
+            let ratio = contrast(*color, Rgb([240, 240, 240]));
+            assert!(ratio >= 4.5, "{color:?} has contrast {ratio:.2}");
+        }
+    }
+
+    #[test]
+    fn it_parses_names() {
+        assert_eq!("pastel".parse::<Palette>(), Ok(Palette::PASTEL));
+        let err = "neon".parse::<Palette>().unwrap_err();
+        assert_eq!(err.to_string(), "unknown palette `neon`");
+    }
+}
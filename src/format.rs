@@ -0,0 +1,252 @@
+use std::io::Cursor;
+use std::str::FromStr;
+
+#[cfg(feature = "formats")]
+use image::codecs::avif::AvifEncoder;
+#[cfg(feature = "formats")]
+use image::codecs::ico::{IcoEncoder, IcoFrame};
+#[cfg(feature = "formats")]
+use image::ExtendedColorType;
+use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+
+use crate::utils::UnknownName;
+use crate::IdenticonOptions;
+
+/// Resolutions packed into [`Format::Ico`] files.
+#[cfg(feature = "formats")]
+pub const FAVICON_SIZES: [u32; 4] = [16, 32, 48, 64];
+
+/// Fastest of rav1e's speeds, 1 to 10.
+#[cfg(feature = "formats")]
+const AVIF_SPEED: u8 = 10;
+#[cfg(feature = "formats")]
+const AVIF_QUALITY: u8 = 80;
+
+/// Size of the PNG iOS expects as an `apple-touch-icon`.
+pub const APPLE_TOUCH_ICON_SIZE: u32 = 180;
+
+/// An encoding identicons can be written out in. Only PNG and SVG are
+/// available without the `formats` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    Png,
+    Svg,
+    #[cfg(feature = "formats")]
+    Webp,
+    #[cfg(feature = "formats")]
+    Avif,
+    #[cfg(feature = "formats")]
+    Jpeg,
+    #[cfg(feature = "formats")]
+    Gif,
+    /// A multi-resolution icon with one frame per [`FAVICON_SIZES`] entry;
+    /// the size in the options is ignored.
+    #[cfg(feature = "formats")]
+    Ico,
+}
+
+impl Format {
+    pub const ALL: &'static [Format] = &[
+        Format::Png,
+        Format::Svg,
+        #[cfg(feature = "formats")]
+        Format::Webp,
+        #[cfg(feature = "formats")]
+        Format::Avif,
+        #[cfg(feature = "formats")]
+        Format::Jpeg,
+        #[cfg(feature = "formats")]
+        Format::Gif,
+        #[cfg(feature = "formats")]
+        Format::Ico,
+    ];
+
+    pub fn from_extension(ext: &str) -> Option<Format> {
+        match ext {
+            "png" => Some(Format::Png),
+            "svg" => Some(Format::Svg),
+            #[cfg(feature = "formats")]
+            "webp" => Some(Format::Webp),
+            #[cfg(feature = "formats")]
+            "avif" => Some(Format::Avif),
+            #[cfg(feature = "formats")]
+            "jpg" | "jpeg" => Some(Format::Jpeg),
+            #[cfg(feature = "formats")]
+            "gif" => Some(Format::Gif),
+            #[cfg(feature = "formats")]
+            "ico" => Some(Format::Ico),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::Svg => "svg",
+            #[cfg(feature = "formats")]
+            Format::Webp => "webp",
+            #[cfg(feature = "formats")]
+            Format::Avif => "avif",
+            #[cfg(feature = "formats")]
+            Format::Jpeg => "jpg",
+            #[cfg(feature = "formats")]
+            Format::Gif => "gif",
+            #[cfg(feature = "formats")]
+            Format::Ico => "ico",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Png => "image/png",
+            Format::Svg => "image/svg+xml",
+            #[cfg(feature = "formats")]
+            Format::Webp => "image/webp",
+            #[cfg(feature = "formats")]
+            Format::Avif => "image/avif",
+            #[cfg(feature = "formats")]
+            Format::Jpeg => "image/jpeg",
+            #[cfg(feature = "formats")]
+            Format::Gif => "image/gif",
+            #[cfg(feature = "formats")]
+            Format::Ico => "image/x-icon",
+        }
+    }
+
+    /// Renders and encodes an identicon for `data`. JPEG has no alpha
+    /// channel, so transparent areas are flattened onto white.
+    pub fn encode(self, options: &IdenticonOptions, data: &[u8]) -> Vec<u8> {
+        match self {
+            Format::Svg => options.gen_svg(data).into_bytes(),
+            #[cfg(feature = "formats")]
+            Format::Ico => favicon(options, data),
+            _ if options.is_opaque() => {
+                write(options.gen(data).into(), self.image_format().unwrap())
+            }
+            _ => self.encode_rgba(options.gen_rgba(data)).unwrap(),
+        }
+    }
+
+    /// Encodes an already rendered image such as an atlas. Returns `None` for
+    /// SVG and ICO, which are built from the identicon.
+    pub fn encode_rgba(self, image: RgbaImage) -> Option<Vec<u8>> {
+        let format = self.image_format()?;
+        #[cfg(feature = "formats")]
+        if self == Format::Jpeg {
+            let image = flatten(image, Rgba([255, 255, 255, 255]));
+            return Some(write(DynamicImage::from(image).to_rgb8().into(), format));
+        }
+        Some(write(image.into(), format))
+    }
+
+    fn image_format(self) -> Option<ImageFormat> {
+        match self {
+            Format::Png => Some(ImageFormat::Png),
+            #[cfg(feature = "formats")]
+            Format::Webp => Some(ImageFormat::WebP),
+            #[cfg(feature = "formats")]
+            Format::Avif => Some(ImageFormat::Avif),
+            #[cfg(feature = "formats")]
+            Format::Jpeg => Some(ImageFormat::Jpeg),
+            #[cfg(feature = "formats")]
+            Format::Gif => Some(ImageFormat::Gif),
+            Format::Svg => None,
+            #[cfg(feature = "formats")]
+            Format::Ico => None,
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Format::from_extension(s).ok_or_else(|| UnknownName::new("format", s))
+    }
+}
+
+pub(crate) fn write(image: DynamicImage, format: ImageFormat) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(3072);
+    #[cfg(feature = "formats")]
+    if format == ImageFormat::Avif {
+        // the default speed takes seconds per large image; flat shapes
+        // gain little from the slower searches anyway
+        let encoder = AvifEncoder::new_with_speed_quality(&mut buf, AVIF_SPEED, AVIF_QUALITY);
+        image.write_with_encoder(encoder).unwrap();
+        return buf;
+    }
+    image.write_to(&mut Cursor::new(&mut buf), format).unwrap();
+    buf
+}
+
+#[cfg(feature = "formats")]
+fn favicon(options: &IdenticonOptions, data: &[u8]) -> Vec<u8> {
+    let identicon = options.identicon(data);
+    let pngs: Vec<_> = FAVICON_SIZES
+        .iter()
+        .map(|size| {
+            let image = identicon.render_rgba(&options.clone().size(*size));
+            (write(image.into(), ImageFormat::Png), *size)
+        })
+        .collect();
+    let frames: Vec<_> = pngs
+        .iter()
+        .map(|(png, size)| IcoFrame::with_encoded(png, *size, *size, ExtendedColorType::Rgba8))
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    let mut buf = Vec::new();
+    IcoEncoder::new(&mut buf).encode_images(&frames).unwrap();
+    buf
+}
+
+/// Composites `image` over an opaque `matte`.
+pub(crate) fn flatten(mut image: RgbaImage, matte: Rgba<u8>) -> RgbaImage {
+    for pixel in image.pixels_mut() {
+        let alpha = pixel.0[3] as u32;
+        for (c, m) in pixel.0.iter_mut().zip(matte.0).take(3) {
+            *c = ((*c as u32 * alpha + m as u32 * (255 - alpha) + 127) / 255) as u8;
+        }
+        pixel.0[3] = 255;
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Format;
+    #[cfg(feature = "formats")]
+    use super::FAVICON_SIZES;
+    use crate::{IdenticonOptions, TRANSPARENT};
+
+    #[test]
+    fn it_encodes_every_format() {
+        let options = IdenticonOptions::new().size(32).background(TRANSPARENT);
+        for format in Format::ALL {
+            let encoded = format.encode(&options, b"abc");
+            match format {
+                Format::Svg => assert!(encoded.starts_with(b"<svg")),
+                #[cfg(feature = "formats")]
+                // not sniffed by this version of `image`
+                Format::Avif => assert_eq!(&encoded[4..12], b"ftypavif"),
+                #[cfg(feature = "formats")]
+                Format::Ico => {
+                    assert_eq!(encoded[4..6], [4, 0]);
+                    for (i, size) in FAVICON_SIZES.into_iter().enumerate() {
+                        let entry = &encoded[6 + 16 * i..][..16];
+                        assert_eq!(entry[..2], [size as u8, size as u8]);
+                        let len = u32::from_le_bytes(entry[8..12].try_into().unwrap());
+                        let offset = u32::from_le_bytes(entry[12..16].try_into().unwrap());
+                        let frame = &encoded[offset as usize..][..len as usize];
+                        let frame = image::load_from_memory(frame).unwrap();
+                        assert_eq!(frame.width(), size);
+                    }
+                }
+                _ => {
+                    let guessed = image::guess_format(&encoded).unwrap();
+                    assert_eq!(guessed.to_mime_type(), format.content_type());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use image::buffer::ConvertBuffer;
+use image::{ImageFormat, Rgb, RgbImage, RgbaImage};
+
+use crate::format;
+use crate::utils::UnknownName;
+
+/// Terminal escape sequences that display a real image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GraphicsProtocol {
+    Sixel,
+    /// The kitty graphics protocol, also understood by WezTerm and Konsole.
+    Kitty,
+    /// iTerm2 inline images, also understood by WezTerm and mintty.
+    Iterm2,
+}
+
+impl GraphicsProtocol {
+    pub fn from_name(name: &str) -> Option<GraphicsProtocol> {
+        match name {
+            "sixel" => Some(GraphicsProtocol::Sixel),
+            "kitty" => Some(GraphicsProtocol::Kitty),
+            "iterm2" | "iterm" => Some(GraphicsProtocol::Iterm2),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for GraphicsProtocol {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        GraphicsProtocol::from_name(s).ok_or_else(|| UnknownName::new("graphics protocol", s))
+    }
+}
+
+/// Largest base64 payload kitty accepts in a single escape.
+const KITTY_CHUNK: usize = 4096;
+
+pub(crate) fn render(image: &RgbaImage, protocol: GraphicsProtocol) -> String {
+    match protocol {
+        // sixel has no partial transparency, so blend onto the usual background
+        GraphicsProtocol::Sixel => {
+            sixel(&format::flatten(image.clone(), crate::BACKGROUND).convert())
+        }
+        GraphicsProtocol::Kitty => {
+            let png = STANDARD.encode(png(image));
+            let mut chunks = png.as_bytes().chunks(KITTY_CHUNK).peekable();
+            let mut out = String::new();
+            let mut first = true;
+            while let Some(chunk) = chunks.next() {
+                let more = chunks.peek().is_some() as u8;
+                out += "\x1b_G";
+                if first {
+                    out += "a=T,f=100,";
+                    first = false;
+                }
+                // base64 is ascii, so chunks split on char boundaries
+                let chunk = std::str::from_utf8(chunk).unwrap();
+                write!(out, "m={};{}\x1b\\", more, chunk).unwrap();
+            }
+            out
+        }
+        GraphicsProtocol::Iterm2 => {
+            let png = png(image);
+            format!(
+                "\x1b]1337;File=inline=1;size={};width={}px;height={}px;preserveAspectRatio=1:{}\x07",
+                png.len(),
+                image.width(),
+                image.height(),
+                STANDARD.encode(&png)
+            )
+        }
+    }
+}
+
+fn png(image: &RgbaImage) -> Vec<u8> {
+    format::write(image.clone().into(), ImageFormat::Png)
+}
+
+/// Emits one pass per color for every band of six rows, run-length encoded.
+fn sixel(image: &RgbImage) -> String {
+    let (width, height) = image.dimensions();
+    let (palette, indices) = palette(image);
+    let mut out = format!("\x1bP0;1;0q\"1;1;{};{}", width, height);
+    for (ix, color) in palette.iter().enumerate() {
+        let [r, g, b] = color.0.map(|c| (c as u32 * 100 + 127) / 255);
+        write!(out, "#{};2;{};{};{}", ix, r, g, b).unwrap();
+    }
+
+    let width = width as usize;
+    for top in (0..height as usize).step_by(6) {
+        let rows = &indices[top * width..((top + 6) * width).min(indices.len())];
+        let mut used = [false; 256];
+        for &ix in rows {
+            used[ix as usize] = true;
+        }
+        let mut first = true;
+        for color in (0..palette.len()).filter(|&c| used[c]) {
+            if !first {
+                out.push('$');
+            }
+            first = false;
+            write!(out, "#{}", color).unwrap();
+            let sixels = (0..width).map(|x| {
+                let bits = rows
+                    .chunks(width)
+                    .enumerate()
+                    .filter(|(_, row)| row[x] as usize == color)
+                    .fold(0, |bits, (dy, _)| bits | 1 << dy);
+                (63 + bits) as u8 as char
+            });
+            run_length(&mut out, sixels);
+        }
+        out.push('-');
+    }
+    out += "\x1b\\";
+    out
+}
+
+fn run_length(out: &mut String, sixels: impl Iterator<Item = char>) {
+    let flush = |out: &mut String, c: char, n: usize| match n {
+        0 => {}
+        1..=3 => out.extend(std::iter::repeat_n(c, n)),
+        _ => write!(out, "!{}{}", n, c).unwrap(),
+    };
+    let mut run = ('?', 0);
+    for c in sixels {
+        if c == run.0 {
+            run.1 += 1;
+        } else {
+            flush(out, run.0, run.1);
+            run = (c, 1);
+        }
+    }
+    flush(out, run.0, run.1);
+}
+
+/// The distinct colors of `image` and each pixel's index into them. Images
+/// with more than 256 colors, such as antialiased ones, are snapped to the
+/// 6x6x6 web-safe cube instead.
+fn palette(image: &RgbImage) -> (Vec<Rgb<u8>>, Vec<u8>) {
+    let mut palette = Vec::new();
+    let mut lookup = HashMap::new();
+    let mut indices = Vec::with_capacity(image.len() / 3);
+    for pixel in image.pixels() {
+        let ix = *lookup.entry(*pixel).or_insert_with(|| {
+            palette.push(*pixel);
+            palette.len() - 1
+        });
+        if ix > 255 {
+            return cube(image);
+        }
+        indices.push(ix as u8);
+    }
+    (palette, indices)
+}
+
+fn cube(image: &RgbImage) -> (Vec<Rgb<u8>>, Vec<u8>) {
+    let palette = (0..216)
+        .map(|ix| Rgb([ix / 36, ix / 6 % 6, ix % 6].map(|level| level as u8 * 51)))
+        .collect();
+    let indices = image
+        .pixels()
+        .map(|pixel| {
+            let [r, g, b] = pixel.0.map(|c| (c as u32 * 5 + 127) / 255);
+            (36 * r + 6 * g + b) as u8
+        })
+        .collect();
+    (palette, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+
+    use image::{Rgba, RgbaImage};
+
+    use super::GraphicsProtocol;
+    use crate::{IdenticonOptions, TRANSPARENT};
+
+    /// Far more colors than a sixel palette holds, and barely compressible.
+    fn noise() -> RgbaImage {
+        let hash = crate::HashAlgorithm::Blake3.digest_stream(b"noise", 64 * 64 * 3);
+        RgbaImage::from_fn(64, 64, |x, y| {
+            let ix = (y * 64 + x) as usize * 3;
+            Rgba([hash[ix], hash[ix + 1], hash[ix + 2], 255])
+        })
+    }
+
+    #[test]
+    fn it_encodes_sixel() {
+        let options = IdenticonOptions::new().size(20);
+        let sixel = options.gen_graphics(b"abc", GraphicsProtocol::Sixel);
+        assert!(sixel.starts_with("\x1bP0;1;0q\"1;1;20;20#0;2;94;94;94#1;2;20;20;40"));
+        assert!(sixel.ends_with("-\x1b\\"));
+        // 20 rows make four bands
+        assert_eq!(sixel.matches('-').count(), 4);
+
+        let sixel = super::render(&noise(), GraphicsProtocol::Sixel);
+        assert!(sixel.contains("#215;2;100;100;100"));
+    }
+
+    #[test]
+    fn it_chunks_kitty_payloads() {
+        let kitty = super::render(&noise(), GraphicsProtocol::Kitty);
+        let chunks: Vec<_> = kitty.split_terminator("\x1b\\").collect();
+        assert!(chunks.len() > 1);
+        assert!(chunks[0].starts_with("\x1b_Ga=T,f=100,m=1;"));
+        assert!(chunks.last().unwrap().starts_with("\x1b_Gm=0;"));
+
+        let payload: String = chunks
+            .iter()
+            .map(|c| c.split_once(';').unwrap().1)
+            .collect();
+        let png = STANDARD.decode(payload).unwrap();
+        let image = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(image, noise());
+    }
+
+    #[test]
+    fn it_keeps_transparency_where_supported() {
+        let options = IdenticonOptions::new().size(20).background(TRANSPARENT);
+        let iterm2 = options.gen_graphics(b"abc", GraphicsProtocol::Iterm2);
+        let (_, payload) = iterm2.trim_end_matches('\x07').split_once(':').unwrap();
+        let png = STANDARD.decode(payload).unwrap();
+        let image = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(image.get_pixel(0, 0).0[3], 0);
+
+        let sixel = options.gen_graphics(b"abc", GraphicsProtocol::Sixel);
+        let opaque = IdenticonOptions::new().size(20);
+        assert_eq!(sixel, opaque.gen_graphics(b"abc", GraphicsProtocol::Sixel));
+    }
+}
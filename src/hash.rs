@@ -0,0 +1,58 @@
+use std::str::FromStr;
+
+use crate::utils::{self, UnknownName};
+
+/// Digest used to seed the sprite pattern and color. Every algorithm yields
+/// at least 16 bytes, which is all the 5x5 sprite consumes; larger grids draw
+/// on [`HashAlgorithm::digest_stream`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum HashAlgorithm {
+    #[default]
+    Md5,
+    Sha256,
+    Blake3,
+    Xxh3,
+}
+
+impl HashAlgorithm {
+    /// Looks up an algorithm by its lowercase name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "md5" => Some(HashAlgorithm::Md5),
+            "sha256" => Some(HashAlgorithm::Sha256),
+            "blake3" => Some(HashAlgorithm::Blake3),
+            "xxh3" => Some(HashAlgorithm::Xxh3),
+            _ => None,
+        }
+    }
+
+    pub fn digest(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Md5 => utils::md5(data).to_vec(),
+            HashAlgorithm::Sha256 => utils::sha256(data).to_vec(),
+            HashAlgorithm::Blake3 => utils::blake3(data).to_vec(),
+            HashAlgorithm::Xxh3 => utils::xxh3(data).to_vec(),
+        }
+    }
+
+    /// The digest of `data` followed by as many re-hashes of the previous
+    /// block as it takes to reach `len` bytes. The first block is always the
+    /// plain digest, so short grids see exactly what [`Self::digest`] returns.
+    pub fn digest_stream(self, data: &[u8], len: usize) -> Vec<u8> {
+        let mut stream = self.digest(data);
+        let mut block = stream.clone();
+        while stream.len() < len {
+            block = self.digest(&block);
+            stream.extend_from_slice(&block);
+        }
+        stream
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        HashAlgorithm::from_name(s).ok_or_else(|| UnknownName::new("hash", s))
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{colors, Rgb, Rgba};
+
+/// Derives the foreground from the hash instead of looking it up in a table:
+/// the hue spans the whole color wheel, while saturation and lightness stay
+/// within the configured percentage ranges.
+///
+/// Lightness is pushed away from the background until the two reach the
+/// minimum contrast ratio, or as far as black or white allow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Hsl {
+    saturation: (u8, u8),
+    lightness: (u8, u8),
+    // hundredths, so the options stay `Eq` and `Hash`
+    min_contrast: u16,
+}
+
+impl Default for Hsl {
+    fn default() -> Self {
+        Hsl {
+            saturation: (45, 75),
+            lightness: (35, 55),
+            min_contrast: 300,
+        }
+    }
+}
+
+impl Hsl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Saturation range in percent, clamped to 0..=100.
+    pub fn saturation(mut self, min: u8, max: u8) -> Self {
+        self.saturation = percent_range(min, max);
+        self
+    }
+
+    /// Lightness range in percent, clamped to 0..=100.
+    pub fn lightness(mut self, min: u8, max: u8) -> Self {
+        self.lightness = percent_range(min, max);
+        self
+    }
+
+    /// WCAG contrast ratio to keep against the background, e.g. `4.5` for
+    /// AA body text. Defaults to `3.0`; `1.0` disables the adjustment.
+    pub fn min_contrast(mut self, ratio: f32) -> Self {
+        self.min_contrast = (ratio.clamp(1.0, 21.0) * 100.0).round() as u16;
+        self
+    }
+
+    /// Picks a color, with its hue turned `hue_offset` degrees further round
+    /// the wheel so secondary tones stay apart from the primary one.
+    pub(crate) fn pick(&self, hash: &[u8], background: Rgba<u8>, hue_offset: f64) -> Rgb<u8> {
+        let hue = u16::from_be_bytes([hash[12], hash[15]]) as f64 / 65536.0 * 360.0;
+        let hue = (hue + hue_offset) % 360.0;
+        let saturation = scale(hash[11], self.saturation);
+        let mut lightness = scale(hash[13], self.lightness);
+        let mut color = hsl_to_rgb(hue, saturation, lightness);
+
+        // a transparent background could end up on anything
+        if background.0[3] == 0 {
+            return color;
+        }
+
+        let background = Rgb([background.0[0], background.0[1], background.0[2]]);
+        let step = if colors::luminance(background) > 0.18 {
+            -1.0
+        } else {
+            1.0
+        };
+        let min_contrast = self.min_contrast as f64 / 100.0;
+        while colors::contrast(color, background) < min_contrast
+            && (0.0..=100.0).contains(&(lightness + step))
+        {
+            lightness += step;
+            color = hsl_to_rgb(hue, saturation, lightness);
+        }
+        color
+    }
+}
+
+fn percent_range(min: u8, max: u8) -> (u8, u8) {
+    let (min, max) = (min.min(100), max.min(100));
+    (min.min(max), min.max(max))
+}
+
+fn scale(byte: u8, (min, max): (u8, u8)) -> f64 {
+    min as f64 + (max - min) as f64 * byte as f64 / 255.0
+}
+
+fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> Rgb<u8> {
+    let s = saturation / 100.0;
+    let l = lightness / 100.0;
+    let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let h = hue / 60.0;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = l - c / 2.0;
+    let channel = |v: f64| ((v + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    Rgb([channel(r), channel(g), channel(b)])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Rgb, Rgba};
+
+    use super::{hsl_to_rgb, Hsl};
+    use crate::{colors, utils};
+
+    #[test]
+    fn it_converts_hsl() {
+        assert_eq!(hsl_to_rgb(0.0, 100.0, 50.0), Rgb([255, 0, 0]));
+        assert_eq!(hsl_to_rgb(120.0, 100.0, 25.0), Rgb([0, 128, 0]));
+        assert_eq!(hsl_to_rgb(240.0, 0.0, 100.0), Rgb([255, 255, 255]));
+    }
+
+    #[test]
+    fn it_keeps_min_contrast() {
+        let hsl = Hsl::new().lightness(60, 90).min_contrast(4.5);
+        for background in [Rgb([240, 240, 240]), Rgb([16, 16, 16])] {
+            let Rgb([r, g, b]) = background;
+            for name in ["abc", "maolonglong", "identicon", ""] {
+                let color = hsl.pick(&utils::md5(name.as_bytes()), Rgba([r, g, b, 255]), 0.0);
+                assert!(colors::contrast(color, background) >= 4.5, "{name:?}");
+            }
+        }
+    }
+}
@@ -1,63 +1,803 @@
-use image::{ImageBuffer, Rgb, RgbImage};
+#[cfg(feature = "image")]
+use image::buffer::ConvertBuffer;
+#[cfg(feature = "image")]
+use image::{ImageBuffer, Pixel, RgbImage, RgbaImage};
 
+#[cfg(feature = "atlas")]
+mod atlas;
+mod color;
 mod colors;
+#[cfg(feature = "image")]
+mod format;
+#[cfg(feature = "image")]
+mod graphics;
+mod hash;
+mod hsl;
 mod nibbler;
+#[cfg(feature = "image")]
+mod raster;
+#[cfg(feature = "server")]
+pub mod server;
+mod shape;
+mod svg;
+mod symmetry;
+mod terminal;
 pub mod utils;
+#[cfg(target_arch = "wasm32")]
+mod wasm;
+
+#[cfg(feature = "atlas")]
+pub use atlas::{Atlas, AtlasEntry, AtlasTooLarge};
+pub use color::{Rgb, Rgba};
+pub use colors::{ColorMode, ColorStrategy, Palette};
+#[cfg(feature = "formats")]
+pub use format::FAVICON_SIZES;
+#[cfg(feature = "image")]
+pub use format::{Format, APPLE_TOUCH_ICON_SIZE};
+#[cfg(feature = "image")]
+pub use graphics::GraphicsProtocol;
+pub use hash::HashAlgorithm;
+pub use hsl::Hsl;
+pub use shape::{CellShape, Direction, Mask};
+pub use symmetry::Symmetry;
+pub use terminal::TerminalMode;
 
 const SPRITE_SIZE: u32 = 5;
 const IMAGE_SIZE: u32 = 290;
-const PIXEL_SIZE: u32 = IMAGE_SIZE / (SPRITE_SIZE + 1);
-const MARGIN: u32 = PIXEL_SIZE / 2;
+const BACKGROUND: Rgba<u8> = Rgba([240, 240, 240, 255]);
+
+/// A fully transparent background for [`IdenticonOptions::background`].
+pub const TRANSPARENT: Rgba<u8> = Rgba([0, 0, 0, 0]);
 
+#[cfg(feature = "image")]
 pub fn gen(data: &[u8]) -> RgbImage {
-    let hash = utils::md5(data);
+    IdenticonOptions::default().gen(data)
+}
+
+#[cfg(feature = "image")]
+pub fn gen_rgba(data: &[u8]) -> RgbaImage {
+    IdenticonOptions::default().gen_rgba(data)
+}
+
+pub fn gen_svg(data: &[u8]) -> String {
+    IdenticonOptions::default().gen_svg(data)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IdenticonOptions {
+    size: u32,
+    margin: Option<u32>,
+    grid: u32,
+    symmetry: Symmetry,
+    background: Rgba<u8>,
+    colors: ColorStrategy,
+    color_mode: ColorMode,
+    shape: CellShape,
+    antialias: bool,
+    mask: Mask,
+    hash: HashAlgorithm,
+}
+
+impl Default for IdenticonOptions {
+    fn default() -> Self {
+        IdenticonOptions {
+            size: IMAGE_SIZE,
+            margin: None,
+            grid: SPRITE_SIZE,
+            symmetry: Symmetry::Horizontal,
+            background: BACKGROUND,
+            colors: ColorStrategy::Palette(Palette::DARK),
+            color_mode: ColorMode::Mono,
+            shape: CellShape::Square,
+            antialias: false,
+            mask: Mask::Square,
+            hash: HashAlgorithm::Md5,
+        }
+    }
+}
+
+impl IdenticonOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Width and height of the generated image in pixels. Images smaller
+    /// than the grid crop the sprite to keep cells at least one pixel.
+    pub fn size(mut self, size: u32) -> Self {
+        assert!(size > 0, "size must be at least 1");
+        self.size = size;
+        self
+    }
+
+    /// Minimum blank border around the sprite in pixels, which centers it.
+    /// Defaults to half a cell before the first one, which is what the
+    /// original fixed 290px layout used; any leftover pixels then go to the
+    /// right and bottom edges.
+    pub fn margin(mut self, margin: u32) -> Self {
+        self.margin = Some(margin);
+        self
+    }
+
+    /// Number of cells along each side of the sprite. Defaults to 5.
+    pub fn grid(mut self, grid: u32) -> Self {
+        assert!(grid > 0, "grid must have at least one cell");
+        self.grid = grid;
+        self
+    }
+
+    /// How the seeded cells are repeated. Defaults to a left-right mirror.
+    pub fn symmetry(mut self, symmetry: Symmetry) -> Self {
+        self.symmetry = symmetry;
+        self
+    }
+
+    /// Color behind the sprite, light grey by default. Use [`TRANSPARENT`]
+    /// (or any partially transparent color) together with
+    /// [`IdenticonOptions::gen_rgba`] to keep the alpha channel.
+    pub fn background(mut self, background: Rgba<u8>) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Colors the foreground is picked from. Defaults to [`Palette::DARK`].
+    pub fn palette(mut self, palette: Palette) -> Self {
+        self.colors = ColorStrategy::Palette(palette);
+        self
+    }
+
+    /// How the foreground is derived, e.g. a [`Palette`] or [`Hsl`] ranges.
+    pub fn colors(mut self, colors: impl Into<ColorStrategy>) -> Self {
+        self.colors = colors.into();
+        self
+    }
+
+    /// How many colors the painted cells are split between. Defaults to a
+    /// single foreground color.
+    pub fn color_mode(mut self, color_mode: ColorMode) -> Self {
+        self.color_mode = color_mode;
+        self
+    }
+
+    /// Whether the rendered image needs no alpha channel.
+    pub fn is_opaque(&self) -> bool {
+        self.background.0[3] == 255 && self.mask == Mask::Square
+    }
+
+    /// What each painted cell is drawn as. Defaults to a plain square.
+    pub fn shape(mut self, shape: CellShape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    /// Smooths raster edges by supersampling, and lays cells out at exact
+    /// fractional positions instead of whole pixels. Off by default, which
+    /// keeps square cells pixel-sharp.
+    pub fn antialias(mut self, antialias: bool) -> Self {
+        self.antialias = antialias;
+        self
+    }
+
+    /// Clips the whole image, e.g. to a circle. Only RGBA and SVG output can
+    /// show the clipped corners; [`IdenticonOptions::gen`] has no alpha.
+    pub fn mask(mut self, mask: Mask) -> Self {
+        self.mask = mask;
+        self
+    }
 
-    let background = Rgb([240, 240, 240]);
-    let foreground = colors::DARK_COLORS
-        [(hash[11] as usize + hash[12] as usize + hash[15] as usize) % colors::DARK_COLORS.len()];
+    /// Digest that seeds the sprite. MD5 keeps existing avatars unchanged.
+    pub fn hash(mut self, hash: HashAlgorithm) -> Self {
+        self.hash = hash;
+        self
+    }
+
+    pub fn identicon(&self, data: &[u8]) -> Identicon {
+        let hash = self
+            .hash
+            .digest_stream(data, Identicon::hash_len(self.grid));
+        Identicon::from_hash_with(&hash, self)
+    }
+
+    #[cfg(feature = "image")]
+    pub fn gen(&self, data: &[u8]) -> RgbImage {
+        self.identicon(data).render_image(self)
+    }
+
+    #[cfg(feature = "image")]
+    pub fn gen_rgba(&self, data: &[u8]) -> RgbaImage {
+        self.identicon(data).render_rgba(self)
+    }
+
+    pub fn gen_svg(&self, data: &[u8]) -> String {
+        self.identicon(data).render_svg(self)
+    }
+
+    pub fn gen_terminal(&self, data: &[u8], mode: TerminalMode) -> String {
+        self.identicon(data).render_terminal(mode)
+    }
+
+    #[cfg(feature = "image")]
+    pub fn gen_graphics(&self, data: &[u8], protocol: GraphicsProtocol) -> String {
+        graphics::render(&self.gen_rgba(data), protocol)
+    }
+
+    fn layout(&self, grid: u32) -> Layout {
+        let margin = self
+            .margin
+            .unwrap_or(self.size / (grid + 1) / 2)
+            // never let the margin squeeze cells below one pixel
+            .min(self.size.saturating_sub(grid) / 2);
+        let cell = ((self.size - 2 * margin) / grid).max(1);
+        // whatever doesn't divide evenly is split between both sides, except
+        // where the default margin is already within a pixel of that, as the
+        // original 290px layout is
+        let centered = self.size.saturating_sub(cell * grid) / 2;
+        let offset = match self.margin {
+            None if margin.abs_diff(centered) <= 1 => margin,
+            _ => centered,
+        };
+        Layout { grid, cell, offset }
+    }
 
-    let mut image: RgbImage = ImageBuffer::from_pixel(IMAGE_SIZE, IMAGE_SIZE, background);
+    /// Like [`IdenticonOptions::layout`] without rounding to whole pixels:
+    /// the cell size and the offset of the first cell.
+    #[cfg(feature = "image")]
+    fn exact_layout(&self, grid: u32) -> (f64, f64) {
+        let size = self.size as f64;
+        let margin = self
+            .margin
+            .map_or(size / (grid + 1) as f64 / 2.0, |margin| margin as f64)
+            .min((size - 1.0).max(0.0) / 2.0);
+        ((size - 2.0 * margin) / grid as f64, margin)
+    }
+}
 
-    for (row, pix) in pixels(hash).chunks(SPRITE_SIZE as usize).enumerate() {
-        for (col, painted) in pix.iter().enumerate() {
-            if *painted {
-                let x = col as u32 * PIXEL_SIZE;
-                let y = row as u32 * PIXEL_SIZE;
-                draw_rect(
-                    &mut image,
-                    x + MARGIN,
-                    y + MARGIN,
-                    x + PIXEL_SIZE + MARGIN,
-                    y + PIXEL_SIZE + MARGIN,
-                    foreground,
-                );
+struct Layout {
+    grid: u32,
+    cell: u32,
+    offset: u32,
+}
+
+impl Layout {
+    /// Index and top-left corner of every painted cell.
+    fn cells<'a>(&'a self, pixels: &'a [bool]) -> impl Iterator<Item = (usize, u32, u32)> + 'a {
+        let grid = self.grid as usize;
+        pixels
+            .iter()
+            .enumerate()
+            .filter(|(_, painted)| **painted)
+            .map(move |(ix, _)| {
+                (
+                    ix,
+                    self.offset + (ix % grid) as u32 * self.cell,
+                    self.offset + (ix / grid) as u32 * self.cell,
+                )
+            })
+    }
+}
+
+/// The pattern and colors of an identicon, independent of how it is drawn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identicon {
+    grid: u32,
+    pixels: Vec<bool>,
+    tones: Vec<u8>,
+    directions: Vec<Direction>,
+    colors: Vec<Rgb<u8>>,
+    background: Rgba<u8>,
+}
+
+impl Identicon {
+    /// Seeds an identicon from `data` using the default MD5 digest.
+    pub fn from_bytes(data: &[u8]) -> Self {
+        Self::from_hash(&utils::md5(data))
+    }
+
+    /// Seeds a 5x5 identicon from an existing digest of at least 16 bytes.
+    pub fn from_hash(hash: &[u8]) -> Self {
+        Self::from_hash_with(hash, &IdenticonOptions::default())
+    }
+
+    /// Seeds an identicon from an existing digest, using the grid, symmetry
+    /// and colors of `options`. The hash algorithm in `options` re-hashes
+    /// `hash` to split the cells between tones and to point triangles.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `hash` is shorter than [`Identicon::hash_len`] for the grid.
+    /// Use [`HashAlgorithm::digest_stream`] to produce enough bytes.
+    pub fn from_hash_with(hash: &[u8], options: &IdenticonOptions) -> Self {
+        let grid = options.grid;
+        assert!(
+            hash.len() >= Self::hash_len(grid),
+            "a {grid}x{grid} grid needs {} hash bytes, got {}",
+            Self::hash_len(grid),
+            hash.len()
+        );
+
+        let symmetry = options.symmetry;
+        let mut nibbles = nibbler::Nibbler::new(hash).map(|x| x % 2 == 0);
+        let pixels = seed(grid, symmetry, |_| nibbles.next().unwrap(), |v, _| v);
+
+        // tones and directions each get their own half of a second stream
+        let len = Self::hash_len(grid);
+        let stream = options.hash.digest_stream(hash, 2 * len);
+        let tones = match options.color_mode {
+            ColorMode::Mono => vec![0; (grid * grid) as usize],
+            mode => {
+                let mut nibbles = nibbler::Nibbler::new(&stream[..len]).map(|x| mode.tone(x));
+                seed(grid, symmetry, |_| nibbles.next().unwrap(), |v, _| v)
             }
+        };
+        let mut nibbles = nibbler::Nibbler::new(&stream[len..]).map(Direction::from_nibble);
+        let directions = seed(
+            grid,
+            symmetry,
+            |fixed| {
+                // cells that map onto themselves need a direction the mapping
+                // leaves alone, or their mirror image would disagree
+                let direction = nibbles.next().unwrap();
+                (0..4)
+                    .map(|turns| direction.transform(symmetry::Transform::Rotate(turns)))
+                    .find(|d| fixed.iter().all(|t| d.transform(*t) == *d))
+                    .unwrap_or(direction)
+            },
+            Direction::transform,
+        );
+
+        Identicon {
+            grid,
+            pixels,
+            tones,
+            directions,
+            colors: options
+                .colors
+                .pick(hash, options.color_mode.colors(), options.background),
+            background: options.background,
+        }
+    }
+
+    /// Minimum digest length needed to seed a `grid` x `grid` identicon with
+    /// any symmetry: one nibble per cell, and never less than the 16 bytes
+    /// the color is picked from.
+    pub fn hash_len(grid: u32) -> usize {
+        let nibbles = grid as usize * grid as usize;
+        nibbles.div_ceil(2).max(16)
+    }
+
+    /// Number of cells along each side of the grid.
+    pub fn grid_size(&self) -> u32 {
+        self.grid
+    }
+
+    /// Row-major cells of the grid, `true` where the foreground is painted.
+    pub fn pixels(&self) -> &[bool] {
+        &self.pixels
+    }
+
+    pub fn is_painted(&self, row: u32, col: u32) -> bool {
+        self.pixels[(row * self.grid + col) as usize]
+    }
+
+    /// The primary foreground color.
+    pub fn foreground(&self) -> Rgb<u8> {
+        self.colors[0]
+    }
+
+    /// Every foreground color, primary first.
+    pub fn colors(&self) -> &[Rgb<u8>] {
+        &self.colors
+    }
+
+    /// Which way the cell points when drawn as a [`CellShape::Triangle`].
+    pub fn direction_at(&self, row: u32, col: u32) -> Direction {
+        self.directions[(row * self.grid + col) as usize]
+    }
+
+    /// The color of a cell, or `None` where the background shows through.
+    pub fn color_at(&self, row: u32, col: u32) -> Option<Rgb<u8>> {
+        let ix = (row * self.grid + col) as usize;
+        self.pixels[ix].then(|| self.colors[self.tones[ix] as usize])
+    }
+
+    pub fn background(&self) -> Rgba<u8> {
+        self.background
+    }
+
+    /// Renders without an alpha channel; a transparent background comes out
+    /// as its color channels alone.
+    #[cfg(feature = "image")]
+    pub fn render_image(&self, options: &IdenticonOptions) -> RgbImage {
+        self.render_rgba(options).convert()
+    }
+
+    #[cfg(feature = "image")]
+    pub fn render_rgba(&self, options: &IdenticonOptions) -> RgbaImage {
+        let mut image = if options.antialias {
+            let (cell, offset) = options.exact_layout(self.grid);
+            raster::render_antialiased(self, options.size, cell, offset, options.shape)
+        } else {
+            self.render_cells(options)
+        };
+        raster::apply_mask(&mut image, options.mask);
+        image
+    }
+
+    #[cfg(feature = "image")]
+    fn render_cells(&self, options: &IdenticonOptions) -> RgbaImage {
+        let layout = options.layout(self.grid);
+        let mut image: RgbaImage =
+            ImageBuffer::from_pixel(options.size, options.size, self.background);
+        let colors: Vec<_> = self.colors.iter().map(|c| c.to_rgba()).collect();
+
+        for (ix, x, y) in layout.cells(&self.pixels) {
+            raster::draw_cell(
+                &mut image,
+                x,
+                y,
+                layout.cell,
+                options.shape,
+                self.directions[ix],
+                colors[self.tones[ix] as usize],
+            );
         }
+
+        image
     }
 
-    image
+    pub fn render_svg(&self, options: &IdenticonOptions) -> String {
+        svg::render(self, options)
+    }
+
+    /// Text art of the grid alone; size, margin, shape and mask don't apply.
+    pub fn render_terminal(&self, mode: TerminalMode) -> String {
+        terminal::render(self, mode)
+    }
 }
 
-fn pixels(hash: [u8; 16]) -> [bool; 25] {
-    let mut nibbles = nibbler::Nibbler::new(&hash).map(|x| x % 2 == 0);
-    let mut pixels = [false; 25];
-    for col in (0..3).rev() {
-        for row in 0..5 {
-            let ix = col + (row * 5);
-            let mirror_col = 4 - col;
-            let mirror_ix = mirror_col + (row * 5);
-            let paint = nibbles.next().unwrap();
-            pixels[ix] = paint;
-            pixels[mirror_ix] = paint;
+/// Fills a `grid` x `grid` board with one value per orbit of `symmetry`.
+///
+/// `next` produces the value of each seed cell, given the transforms that map
+/// that cell onto itself, and `place` carries it onto the rest of the orbit.
+fn seed<T: Copy + Default>(
+    grid: u32,
+    symmetry: Symmetry,
+    mut next: impl FnMut(&[symmetry::Transform]) -> T,
+    place: impl Fn(T, symmetry::Transform) -> T,
+) -> Vec<T> {
+    let grid = grid as usize;
+    let half = grid.div_ceil(2);
+    let mut cells = vec![T::default(); grid * grid];
+    let mut seeded = vec![false; grid * grid];
+    // center-out through the left half first, which for the horizontal mirror
+    // is every seed and matches the original 5x5 nibble order
+    let cols = (0..half).rev().chain(half..grid);
+    for col in cols {
+        for row in 0..grid {
+            if seeded[col + (row * grid)] {
+                continue;
+            }
+            let orbit = symmetry.orbit(grid, row, col);
+            let fixed: Vec<_> = orbit
+                .iter()
+                .skip(1)
+                .filter(|(r, c, _)| (*r, *c) == (row, col))
+                .map(|(_, _, t)| *t)
+                .collect();
+            let value = next(&fixed);
+            for (row, col, transform) in orbit {
+                let ix = col + (row * grid);
+                // the seed cell comes first; later self-maps must not move it
+                if !seeded[ix] {
+                    cells[ix] = place(value, transform);
+                    seeded[ix] = true;
+                }
+            }
         }
     }
-    pixels
+    cells
 }
 
-fn draw_rect(image: &mut RgbImage, x0: u32, y0: u32, x1: u32, y1: u32, color: Rgb<u8>) {
-    for x in x0..x1 {
-        for y in y0..y1 {
-            image.put_pixel(x, y, color);
+#[cfg(test)]
+mod tests {
+    use super::{CellShape, ColorMode, Direction, Identicon, IdenticonOptions, Rgb, Symmetry};
+
+    fn grid(pixels: &[bool]) -> String {
+        pixels.iter().map(|p| if *p { '#' } else { '.' }).collect()
+    }
+
+    #[test]
+    fn it_matches_md5_golden_sprites() {
+        let golden = [
+            ("abc", Rgb([0x33, 0x33, 0x66]), ".#.#.#.#.######......#.#."),
+            (
+                "maolonglong",
+                Rgb([0xcc, 0xcc, 0x00]),
+                "##.##.#.#.#####.###...#..",
+            ),
+            ("", Rgb([0x00, 0x99, 0xcc]), "##.###.#.#.....##.###.#.#"),
+        ];
+        for (name, color, pattern) in golden {
+            let identicon = Identicon::from_bytes(name.as_bytes());
+            assert_eq!(identicon.foreground(), color, "{name:?}");
+            assert_eq!(grid(identicon.pixels()), pattern, "{name:?}");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn it_matches_md5_golden_images() {
+        // raw RGB digests of the renderer before options existed
+        let golden = [
+            ("abc", "b57273766cb2794797ffc403ffe8abf6"),
+            ("maolonglong", "134121d0d53ce6015ff47f01c981b932"),
+            ("", "115af1ebf947ad59c6d99ff5104ed578"),
+            ("hello", "35b9a4fce0ef88ad9293efd145b3d672"),
+        ];
+        for (name, digest) in golden {
+            let image = super::gen(name.as_bytes());
+            assert_eq!(
+                hex::encode(super::utils::md5(image.as_raw())),
+                digest,
+                "{name:?}"
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn it_defaults_to_md5() {
+        let options = IdenticonOptions::new();
+        assert_eq!(
+            options.gen(b"abc"),
+            options.clone().hash(super::HashAlgorithm::Md5).gen(b"abc")
+        );
+        assert_ne!(
+            options.gen(b"abc"),
+            options
+                .clone()
+                .hash(super::HashAlgorithm::Sha256)
+                .gen(b"abc")
+        );
+    }
+
+    #[test]
+    fn it_fits_sprite_for_any_size() {
+        for size in [16, 32, 64, 100, 128, 290, 500, 512, 1000] {
+            for options in [
+                IdenticonOptions::new().size(size),
+                IdenticonOptions::new().size(size).margin(4),
+            ] {
+                let layout = options.layout(5);
+                let used = layout.cell * 5;
+                assert!(layout.cell > 0);
+                assert!(layout.offset + used <= size);
+                let centered = (size - used) / 2;
+                assert!(layout.offset.abs_diff(centered) <= 1, "size {size}");
+            }
+        }
+    }
+
+    #[test]
+    fn it_keeps_original_layout() {
+        let layout = IdenticonOptions::new().layout(5);
+        assert_eq!((layout.cell, layout.offset), (48, 24));
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn it_keeps_cells_for_tiny_sizes() {
+        for size in 1..5 {
+            let image = IdenticonOptions::new().size(size).gen(b"abc");
+            assert_eq!(image.dimensions(), (size, size));
+        }
+        let image = IdenticonOptions::new().size(5).gen(b"abc");
+        assert!(image.pixels().any(|p| p.0 != [240, 240, 240]));
+    }
+
+    #[test]
+    fn it_clamps_oversized_margin() {
+        let layout = IdenticonOptions::new().size(32).margin(100).layout(5);
+        assert_eq!(layout.cell, 1);
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn it_renders_requested_size() {
+        let image = IdenticonOptions::new().size(64).gen(b"abc");
+        assert_eq!(image.dimensions(), (64, 64));
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn it_paints_same_cells_in_svg_and_png() {
+        let options = IdenticonOptions::new().size(64);
+        let image = options.gen(b"abc");
+        let svg = options.gen_svg(b"abc");
+        let background = Rgb([240, 240, 240]);
+        let painted = image.pixels().filter(|p| **p != background).count() as u32;
+        let cell = options.layout(5).cell;
+        assert_eq!(
+            painted,
+            svg.matches("<rect x=").count() as u32 * cell * cell
+        );
+    }
+
+    #[test]
+    fn it_mirrors_odd_and_even_grids() {
+        for grid in [1, 2, 7, 9, 12, 16] {
+            let identicon = IdenticonOptions::new().grid(grid).identicon(b"abc");
+            assert_eq!(identicon.pixels().len(), (grid * grid) as usize);
+            for row in 0..grid {
+                for col in 0..grid {
+                    assert_eq!(
+                        identicon.is_painted(row, col),
+                        identicon.is_painted(row, grid - 1 - col)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn it_extends_short_digests_for_large_grids() {
+        let options = IdenticonOptions::new().grid(12);
+        assert!(Identicon::hash_len(12) > 16);
+        assert_eq!(options.identicon(b"abc"), options.identicon(b"abc"));
+        assert_ne!(
+            options.identicon(b"abc").pixels(),
+            options.identicon(b"abd").pixels()
+        );
+    }
+
+    #[test]
+    fn it_applies_symmetries() {
+        let symmetries = [
+            Symmetry::Horizontal,
+            Symmetry::Vertical,
+            Symmetry::FourWay,
+            Symmetry::Rotational180,
+            Symmetry::Rotational90,
+            Symmetry::None,
+        ];
+        for grid in [5, 6] {
+            for symmetry in symmetries {
+                let options = IdenticonOptions::new().grid(grid).symmetry(symmetry);
+                let identicon = options.identicon(b"maolonglong");
+                let n = grid - 1;
+                for r in 0..grid {
+                    for c in 0..grid {
+                        let cell = identicon.is_painted(r, c);
+                        let expected = match symmetry {
+                            Symmetry::Horizontal => identicon.is_painted(r, n - c),
+                            Symmetry::Vertical => identicon.is_painted(n - r, c),
+                            Symmetry::FourWay => {
+                                identicon.is_painted(n - r, c) && identicon.is_painted(r, n - c)
+                            }
+                            Symmetry::Rotational180 => identicon.is_painted(n - r, n - c),
+                            Symmetry::Rotational90 => identicon.is_painted(c, n - r),
+                            Symmetry::None => cell,
+                        };
+                        assert_eq!(cell, expected, "{symmetry:?} {grid}x{grid}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn it_renders_transparent_background() {
+        let options = IdenticonOptions::new()
+            .size(64)
+            .background(super::TRANSPARENT);
+        let image = options.gen_rgba(b"abc");
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0, 0]);
+        assert!(image.pixels().any(|p| p.0[3] == 255));
+        assert!(!options.gen_svg(b"abc").contains(r#"<rect width="#));
+    }
+
+    #[test]
+    fn it_splits_cells_between_tones() {
+        for mode in [ColorMode::TwoTone, ColorMode::ThreeTone, ColorMode::Accent] {
+            let options = IdenticonOptions::new().grid(7).color_mode(mode);
+            let identicon = options.identicon(b"maolonglong");
+            let colors = identicon.colors();
+            assert_eq!(
+                colors[0],
+                IdenticonOptions::new()
+                    .identicon(b"maolonglong")
+                    .foreground()
+            );
+            for (i, color) in colors.iter().enumerate() {
+                assert!(!colors[..i].contains(color), "{mode:?} repeats {color:?}");
+            }
+            for row in 0..7 {
+                for col in 0..7 {
+                    assert_eq!(
+                        identicon.color_at(row, col),
+                        identicon.color_at(row, 6 - col)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn it_draws_cell_shapes() {
+        let square = IdenticonOptions::new().background(super::TRANSPARENT);
+        let painted = |options: &IdenticonOptions| {
+            options
+                .gen_rgba(b"abc")
+                .pixels()
+                .filter(|p| p.0[3] != 0)
+                .count() as f64
+        };
+        let full = painted(&square);
+        for (shape, area) in [
+            (CellShape::Circle, std::f64::consts::PI / 4.0),
+            (CellShape::Diamond, 0.5),
+            (CellShape::Triangle, 0.5),
+        ] {
+            let ratio = painted(&square.clone().shape(shape)) / full;
+            assert!((ratio - area).abs() < 0.05, "{shape:?} covers {ratio:.3}");
+        }
+        assert!(square
+            .clone()
+            .shape(CellShape::Circle)
+            .gen_svg(b"abc")
+            .contains("<circle"));
+    }
+
+    #[test]
+    fn it_mirrors_triangle_directions() {
+        let options = IdenticonOptions::new()
+            .shape(CellShape::Triangle)
+            .symmetry(Symmetry::FourWay);
+        for name in ["abc", "maolonglong"] {
+            let identicon = options.identicon(name.as_bytes());
+            for row in 0..5 {
+                // no direction survives both mirrors in the very center
+                for col in (0..5).filter(|col| (row, *col) != (2, 2)) {
+                    let mirrored = identicon.direction_at(row, 4 - col);
+                    let expected = match identicon.direction_at(row, col) {
+                        Direction::Left => Direction::Right,
+                        Direction::Right => Direction::Left,
+                        direction => direction,
+                    };
+                    assert_eq!(mirrored, expected, "{name:?} ({row}, {col})");
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn it_antialiases_edges() {
+        let options = IdenticonOptions::new()
+            .size(100)
+            .antialias(true)
+            .background(super::TRANSPARENT);
+        let square = options.gen_rgba(b"abc");
+        // exact layout keeps the mirrored sprite centered at any size
+        assert_eq!(square, image::imageops::flip_horizontal(&square));
+
+        let circle = options.clone().shape(CellShape::Circle).gen_rgba(b"abc");
+        assert!(circle.pixels().any(|p| p.0[3] > 0 && p.0[3] < 255));
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn it_masks_corners() {
+        use super::Mask;
+
+        for mask in [Mask::Circle, Mask::Rounded(50), Mask::Squircle] {
+            let options = IdenticonOptions::new().size(64).mask(mask);
+            assert!(!options.is_opaque());
+            let image = options.gen_rgba(b"abc");
+            assert_eq!(image.get_pixel(0, 0).0[3], 0, "{mask:?}");
+            assert_eq!(image.get_pixel(32, 0).0[3], 255, "{mask:?}");
+            assert!(options.gen_svg(b"abc").contains("<clipPath"));
         }
     }
 }
@@ -6,7 +6,7 @@ pub(crate) struct Nibbler<'a> {
 }
 
 impl<'a> Nibbler<'a> {
-    pub(crate) fn new(bytes: &[u8]) -> Nibbler {
+    pub(crate) fn new(bytes: &[u8]) -> Nibbler<'_> {
         Nibbler {
             bytes: bytes.iter(),
             byte: None,
@@ -0,0 +1,113 @@
+use image::{ImageBuffer, Pixel, Rgba, RgbaImage};
+
+use crate::{CellShape, Direction, Identicon, Mask};
+
+/// Samples per pixel along each axis when anti-aliasing.
+const SAMPLES: u32 = 4;
+
+/// Fills the pixels of a `size` x `size` cell whose centers fall inside
+/// `shape`.
+pub(crate) fn draw_cell(
+    image: &mut RgbaImage,
+    x0: u32,
+    y0: u32,
+    size: u32,
+    shape: CellShape,
+    direction: Direction,
+    color: Rgba<u8>,
+) {
+    let scale = size as f64;
+    for x in 0..size {
+        for y in 0..size {
+            let (cx, cy) = ((x as f64 + 0.5) / scale, (y as f64 + 0.5) / scale);
+            if !shape.contains(direction, cx, cy) {
+                continue;
+            }
+            // cells of images smaller than the grid hang off the edge
+            if let Some(pixel) = image.get_pixel_mut_checked(x0 + x, y0 + y) {
+                *pixel = color;
+            }
+        }
+    }
+}
+
+/// Scales the alpha of every pixel by how much of it `mask` covers.
+pub(crate) fn apply_mask(image: &mut RgbaImage, mask: Mask) {
+    if mask == Mask::Square {
+        return;
+    }
+
+    let size = image.width() as f64;
+    for (px, py, pixel) in image.enumerate_pixels_mut() {
+        let mut covered = 0;
+        for i in 0..SAMPLES {
+            for j in 0..SAMPLES {
+                let x = (px as f64 + (i as f64 + 0.5) / SAMPLES as f64) / size;
+                let y = (py as f64 + (j as f64 + 0.5) / SAMPLES as f64) / size;
+                covered += mask.contains(x, y) as u32;
+            }
+        }
+        let n = SAMPLES * SAMPLES;
+        pixel.0[3] = ((pixel.0[3] as u32 * covered + n / 2) / n) as u8;
+    }
+}
+
+/// Renders with cells of fractional `cell` size starting `offset` pixels in,
+/// averaging `SAMPLES` x `SAMPLES` points per pixel so edges that cut through
+/// a pixel blend instead of snapping.
+pub(crate) fn render_antialiased(
+    identicon: &Identicon,
+    size: u32,
+    cell: f64,
+    offset: f64,
+    shape: CellShape,
+) -> RgbaImage {
+    let grid = identicon.grid as usize;
+    let colors: Vec<_> = identicon.colors.iter().map(|c| c.to_rgba()).collect();
+    let sample = |x: f64, y: f64| {
+        let (gx, gy) = ((x - offset) / cell, (y - offset) / cell);
+        if gx < 0.0 || gy < 0.0 {
+            return identicon.background;
+        }
+        let (col, row) = (gx as usize, gy as usize);
+        if col >= grid || row >= grid {
+            return identicon.background;
+        }
+        let ix = row * grid + col;
+        let inside = identicon.pixels[ix]
+            && shape.contains(identicon.directions[ix], gx - col as f64, gy - row as f64);
+        if inside {
+            colors[identicon.tones[ix] as usize]
+        } else {
+            identicon.background
+        }
+    };
+
+    ImageBuffer::from_fn(size, size, |px, py| {
+        // average in premultiplied alpha so transparent samples don't darken
+        let mut sum = [0u32; 4];
+        for i in 0..SAMPLES {
+            for j in 0..SAMPLES {
+                let x = px as f64 + (i as f64 + 0.5) / SAMPLES as f64;
+                let y = py as f64 + (j as f64 + 0.5) / SAMPLES as f64;
+                let Rgba([r, g, b, a]) = sample(x, y);
+                let a = a as u32;
+                sum[0] += r as u32 * a;
+                sum[1] += g as u32 * a;
+                sum[2] += b as u32 * a;
+                sum[3] += a;
+            }
+        }
+        if sum[3] == 0 {
+            return Rgba([0, 0, 0, 0]);
+        }
+        let n = SAMPLES * SAMPLES;
+        let channel = |c: u32| ((c + sum[3] / 2) / sum[3]) as u8;
+        Rgba([
+            channel(sum[0]),
+            channel(sum[1]),
+            channel(sum[2]),
+            ((sum[3] + n / 2) / n) as u8,
+        ])
+    })
+}
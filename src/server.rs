@@ -0,0 +1,503 @@
+use std::borrow::Cow;
+use std::convert::Infallible;
+use std::mem;
+use std::sync::Arc;
+
+use axum::error_handling::HandleErrorLayer;
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{BoxError, Router};
+use bytes::Bytes;
+use faststr::FastStr;
+use quick_cache::sync::Cache;
+use quick_cache::Weighter;
+use serde::Deserialize;
+use tower::ServiceBuilder;
+use tower_http::trace::TraceLayer;
+use tracing::{debug, instrument};
+
+use crate::{utils, CellShape, Format, IdenticonOptions, Mask, Palette, APPLE_TOUCH_ICON_SIZE};
+
+mod config;
+
+pub use config::Config;
+
+type AppState = Arc<Shared>;
+
+struct Shared {
+    cache: Cache<CacheKey, CacheEntry, EntryWeighter>,
+    config: Config,
+    cache_control: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    name: FastStr,
+    options: IdenticonOptions,
+    format: Format,
+}
+
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    image: Bytes,
+    etag: FastStr,
+}
+
+/// Typical size of an encoded response, for sizing the cache's tables.
+const AVERAGE_ENTRY_BYTES: u64 = 8 * 1024;
+
+/// Weighs entries by the bytes they hold, so the cache's capacity is a memory
+/// budget rather than a count.
+#[derive(Debug, Clone)]
+struct EntryWeighter;
+
+impl Weighter<CacheKey, CacheEntry> for EntryWeighter {
+    fn weight(&self, key: &CacheKey, entry: &CacheEntry) -> u32 {
+        let bytes = mem::size_of::<(CacheKey, CacheEntry)>()
+            + key.name.len()
+            + entry.image.len()
+            + entry.etag.len();
+        bytes.try_into().unwrap_or(u32::MAX)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ImageQuery {
+    #[serde(alias = "s")]
+    size: Option<u32>,
+    bg: Option<FastStr>,
+    palette: Option<FastStr>,
+    shape: Option<FastStr>,
+    mask: Option<FastStr>,
+    antialias: Option<bool>,
+}
+
+#[instrument(skip_all)]
+async fn gen_image(
+    Path(name): Path<FastStr>,
+    Query(query): Query<ImageQuery>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Response {
+    if name == "favicon.ico" {
+        return not_found().await.into_response();
+    }
+
+    let enabled = &state.config.formats;
+    let (name, format) = match name.rsplit_once('.') {
+        Some((stem, ext)) => match Format::from_extension(ext) {
+            Some(format) if enabled.contains(&format) => (name.slice_ref(stem), format),
+            Some(_) => return not_found().await.into_response(),
+            None => (name, negotiate(&headers, enabled)),
+        },
+        None => (name, negotiate(&headers, enabled)),
+    };
+
+    match image_options(&query, &state.config) {
+        Ok(options) => serve(name, options, format, &headers, &state).await,
+        Err(message) => (StatusCode::BAD_REQUEST, message).into_response(),
+    }
+}
+
+#[instrument(skip_all)]
+async fn gen_favicon(
+    Path(name): Path<FastStr>,
+    Query(query): Query<ImageQuery>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Response {
+    if !state.config.is_enabled(Format::Ico) {
+        return not_found().await.into_response();
+    }
+
+    // every frame is rendered at its own size, so neither reject nor cache by
+    // the requested one
+    let query = ImageQuery {
+        size: None,
+        ..query
+    };
+    match image_options(&query, &state.config) {
+        Ok(options) => serve(name, options, Format::Ico, &headers, &state).await,
+        Err(message) => (StatusCode::BAD_REQUEST, message).into_response(),
+    }
+}
+
+#[instrument(skip_all)]
+async fn gen_touch_icon(
+    Path(name): Path<FastStr>,
+    Query(query): Query<ImageQuery>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Response {
+    if !state.config.is_enabled(Format::Png) {
+        return not_found().await.into_response();
+    }
+
+    let query = ImageQuery {
+        size: None,
+        ..query
+    };
+    match image_options(&query, &state.config) {
+        Ok(options) => {
+            let options = options.size(APPLE_TOUCH_ICON_SIZE);
+            serve(name, options, Format::Png, &headers, &state).await
+        }
+        Err(message) => (StatusCode::BAD_REQUEST, message).into_response(),
+    }
+}
+
+async fn serve(
+    name: FastStr,
+    options: IdenticonOptions,
+    format: Format,
+    headers: &HeaderMap,
+    state: &Shared,
+) -> Response {
+    // every distinct name is a new cache entry, so don't let them grow unbounded
+    if name.len() > state.config.max_name_len {
+        let message = format!("name must be at most {} bytes", state.config.max_name_len);
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    }
+
+    let key = CacheKey {
+        name: name.clone(),
+        options,
+        format,
+    };
+    let entry = state
+        .cache
+        .get_or_insert_async(&key, async {
+            debug!("cache missing");
+            // encoding can take long enough to starve the runtime, and off of
+            // it the timeout can still cancel the request
+            let options = key.options.clone();
+            let data = name.clone();
+            let buf = tokio::task::spawn_blocking(move || format.encode(&options, data.as_bytes()))
+                .await
+                .unwrap();
+
+            let hash = utils::md5(&buf);
+
+            Ok::<_, Infallible>(CacheEntry {
+                image: buf.into(),
+                etag: hex::encode(hash).into(),
+            })
+        })
+        .await
+        .unwrap();
+
+    let response_headers = [
+        (header::CONTENT_TYPE, format.content_type()),
+        (header::VARY, "Accept"),
+        (header::CACHE_CONTROL, &state.cache_control),
+        (header::ETAG, &entry.etag),
+    ];
+
+    if let Some(etag) = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|x| x.to_str().ok())
+    {
+        if etag == entry.etag {
+            debug!("etag matched");
+            return (response_headers, StatusCode::NOT_MODIFIED).into_response();
+        }
+    }
+
+    (response_headers, entry.image).into_response()
+}
+
+fn image_options(query: &ImageQuery, config: &Config) -> Result<IdenticonOptions, String> {
+    let mut options = config.image_options();
+
+    if let Some(size) = query.size {
+        if !(config.min_size..=config.max_size).contains(&size) {
+            return Err(format!(
+                "size must be between {} and {}",
+                config.min_size, config.max_size
+            ));
+        }
+        options = options.size(size);
+    }
+
+    if let Some(bg) = &query.bg {
+        let background = config::parse_color(bg).map_err(|err| format!("bg {}", err))?;
+        options = options.background(background);
+    }
+
+    if let Some(palette) = &query.palette {
+        options = options.palette(palette.parse::<Palette>().map_err(|err| err.to_string())?);
+    }
+
+    if let Some(shape) = &query.shape {
+        options = options.shape(shape.parse::<CellShape>().map_err(|err| err.to_string())?);
+    }
+
+    if let Some(mask) = &query.mask {
+        options = options.mask(mask.parse::<Mask>().map_err(|err| err.to_string())?);
+    }
+
+    if let Some(antialias) = query.antialias {
+        options = options.antialias(antialias);
+    }
+
+    Ok(options)
+}
+
+fn negotiate(headers: &HeaderMap, enabled: &[Format]) -> Format {
+    let accept = headers.get(header::ACCEPT).and_then(|x| x.to_str().ok());
+    negotiate_accept(accept, enabled)
+}
+
+/// Picks the format an `Accept` header rates highest.
+///
+/// Each format takes the quality of the most specific range that matches it,
+/// so `image/png;q=0.5, image/*` ranks PNG below the rest. Ties go to formats
+/// the client named outright, then to the order below: browsers that list
+/// `image/webp` get WebP, and anything only sending `image/*` keeps PNG.
+/// Only `enabled` formats are considered; when none of them match, the
+/// first in that order wins anyway.
+fn negotiate_accept(accept: Option<&str>, enabled: &[Format]) -> Format {
+    const PREFERENCE: [Format; 6] = [
+        Format::Png,
+        Format::Webp,
+        Format::Avif,
+        Format::Svg,
+        Format::Jpeg,
+        Format::Gif,
+    ];
+
+    let fallback = PREFERENCE
+        .into_iter()
+        .find(|format| enabled.contains(format))
+        .unwrap_or(enabled[0]);
+    let Some(accept) = accept else {
+        return fallback;
+    };
+
+    // (specificity, quality) of the best matching range for each format
+    let mut ranks = [(0u8, 0.0f32); PREFERENCE.len()];
+    for range in accept.split(',') {
+        let mut params = range.split(';');
+        let mime = params.next().unwrap_or_default().trim();
+        let q = params
+            .filter_map(|p| p.trim().strip_prefix("q="))
+            .find_map(|q| q.parse::<f32>().ok())
+            .unwrap_or(1.0);
+        for (format, rank) in PREFERENCE.iter().zip(&mut ranks) {
+            let specificity = match mime {
+                "*/*" => 1,
+                "image/*" => 2,
+                mime if mime == format.content_type() => 3,
+                _ => continue,
+            };
+            if specificity > rank.0 {
+                *rank = (specificity, q);
+            }
+        }
+    }
+
+    let mut best = (fallback, 0.0, 0);
+    for (format, (specificity, q)) in PREFERENCE.into_iter().zip(ranks) {
+        if !enabled.contains(&format) {
+            continue;
+        }
+        let explicit = (specificity == 3) as u8;
+        if q > best.1 || (q == best.1 && q > 0.0 && explicit > best.2) {
+            best = (format, q, explicit);
+        }
+    }
+    best.0
+}
+
+async fn not_found() -> impl IntoResponse {
+    (StatusCode::NOT_FOUND, "nothing to see here")
+}
+
+async fn handle_error(error: BoxError) -> impl IntoResponse {
+    if error.is::<tower::timeout::error::Elapsed>() {
+        return (StatusCode::REQUEST_TIMEOUT, Cow::from("request timed out"));
+    }
+
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Cow::from(format!("unhandled internal error: {}", error)),
+    )
+}
+
+/// The identicon service: `/:name` with optional extension, plus favicon and
+/// touch icon routes. Shared by the Shuttle and self-hosted entry points.
+pub fn router(config: Config) -> Router {
+    let state = Shared {
+        cache: Cache::with_weighter(
+            (config.cache_bytes / AVERAGE_ENTRY_BYTES).max(1) as usize,
+            config.cache_bytes,
+            EntryWeighter,
+        ),
+        cache_control: format!("public, max-age={}", config.max_age_secs),
+        config,
+    };
+    app(Arc::new(state))
+}
+
+fn app(state: AppState) -> Router {
+    let timeout = state.config.timeout();
+    Router::new()
+        .route("/:name", get(gen_image))
+        .route("/:name/favicon.ico", get(gen_favicon))
+        .route("/:name/apple-touch-icon.png", get(gen_touch_icon))
+        .fallback(not_found)
+        .layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(handle_error))
+                .timeout(timeout)
+                .layer(TraceLayer::new_for_http()),
+        )
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::body::Body;
+    use axum::http::{header, Request, StatusCode};
+    use axum::response::Response;
+    use axum::Router;
+    use quick_cache::sync::Cache;
+    use quick_cache::Weighter;
+    use tower::ServiceExt;
+
+    use super::{
+        app, negotiate_accept, AppState, CacheEntry, CacheKey, Config, EntryWeighter, Shared,
+    };
+    use crate::{Format, IdenticonOptions};
+
+    fn state() -> AppState {
+        Arc::new(Shared {
+            cache: Cache::with_weighter(16, 1 << 20, EntryWeighter),
+            config: Config::default(),
+            cache_control: String::new(),
+        })
+    }
+
+    async fn get(app: &Router, uri: &str) -> Response {
+        let request = Request::get(uri).body(Body::empty()).unwrap();
+        app.clone().oneshot(request).await.unwrap()
+    }
+
+    fn etag(response: &Response) -> &str {
+        response.headers()[header::ETAG].to_str().unwrap()
+    }
+
+    #[test]
+    fn it_negotiates_formats() {
+        let negotiate_accept = |accept| negotiate_accept(accept, Format::ALL);
+        assert_eq!(negotiate_accept(None), Format::Png);
+        assert_eq!(negotiate_accept(Some("image/svg+xml")), Format::Svg);
+        assert_eq!(
+            negotiate_accept(Some("image/svg+xml,image/*,*/*;q=0.8")),
+            Format::Svg
+        );
+        assert_eq!(
+            negotiate_accept(Some("image/png;q=0.5, image/*")),
+            Format::Webp
+        );
+        assert_eq!(
+            negotiate_accept(Some(
+                "image/avif,image/webp,image/apng,image/svg+xml,image/*,*/*;q=0.8"
+            )),
+            Format::Webp
+        );
+        assert_eq!(negotiate_accept(Some("image/*")), Format::Png);
+        assert_eq!(negotiate_accept(Some("text/html")), Format::Png);
+    }
+
+    #[test]
+    fn it_negotiates_enabled_formats() {
+        let enabled = [Format::Svg, Format::Webp];
+        assert_eq!(negotiate_accept(None, &enabled), Format::Webp);
+        assert_eq!(negotiate_accept(Some("image/png"), &enabled), Format::Webp);
+        assert_eq!(
+            negotiate_accept(Some("image/png, image/svg+xml;q=0.5"), &enabled),
+            Format::Svg
+        );
+        assert_eq!(negotiate_accept(Some("*/*"), &[Format::Ico]), Format::Ico);
+    }
+
+    #[test]
+    fn it_weighs_entries_by_bytes() {
+        let weigh = |name: &'static str, image: Vec<u8>| {
+            let key = CacheKey {
+                name: name.into(),
+                options: IdenticonOptions::new(),
+                format: Format::Png,
+            };
+            let entry = CacheEntry {
+                image: image.into(),
+                etag: "d41d8cd98f00b204e9800998ecf8427e".into(),
+            };
+            EntryWeighter.weight(&key, &entry)
+        };
+        let small = weigh("abc", vec![0; 100]);
+        assert_eq!(weigh("abc", vec![0; 100_100]), small + 100_000);
+        assert_eq!(weigh("abcdef", vec![0; 100]), small + 3);
+    }
+
+    #[tokio::test]
+    async fn it_rejects_sizes_out_of_range() {
+        let app = app(state());
+        for uri in ["/abc?s=0", "/abc?s=8", "/abc?size=5000", "/abc?s=abc"] {
+            assert_eq!(
+                get(&app, uri).await.status(),
+                StatusCode::BAD_REQUEST,
+                "{uri}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn it_caches_each_size_separately() {
+        let state = state();
+        let app = app(state.clone());
+
+        let large = get(&app, "/abc?s=64").await;
+        let small = get(&app, "/abc?s=32").await;
+        let alias = get(&app, "/abc?size=64").await;
+        assert_eq!(large.status(), StatusCode::OK);
+        assert_eq!(small.status(), StatusCode::OK);
+        assert_ne!(etag(&large), etag(&small));
+        assert_eq!(etag(&large), etag(&alias));
+        assert_eq!(
+            get(&app, "/abc?s=0").await.status(),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(state.cache.len(), 2);
+
+        let body = axum::body::to_bytes(large.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let image = image::load_from_memory(&body).unwrap();
+        assert_eq!((image.width(), image.height()), (64, 64));
+    }
+
+    #[tokio::test]
+    async fn it_serves_icons_at_fixed_sizes() {
+        let app = app(state());
+
+        let touch = get(&app, "/abc/apple-touch-icon.png?s=64").await;
+        assert_eq!(touch.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(touch.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let image = image::load_from_memory(&body).unwrap();
+        assert_eq!((image.width(), image.height()), (180, 180));
+
+        let favicon = get(&app, "/abc/favicon.ico").await;
+        assert_eq!(favicon.status(), StatusCode::OK);
+        for uri in ["/abc/favicon.ico?s=64", "/abc/favicon.ico?s=0"] {
+            assert_eq!(etag(&get(&app, uri).await), etag(&favicon), "{uri}");
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use std::env;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::{de, Deserialize, Deserializer};
+use tracing::warn;
+
+use crate::utils::{self, UnknownName};
+use crate::{CellShape, Format, IdenticonOptions, Mask, Palette, Rgba};
+
+/// Read when `IDENTICON_CONFIG` doesn't point somewhere else.
+const DEFAULT_FILE: &str = "identicon.toml";
+
+/// Largest `max_size` accepted. AVIF, the slowest format, takes about two
+/// seconds at this size, and four times as long per doubling beyond it.
+const SIZE_LIMIT: u32 = 2048;
+
+/// Settings [`Config::set`] understands.
+const KEYS: &[&str] = &[
+    "host",
+    "port",
+    "cache_bytes",
+    "max_name_len",
+    "timeout_secs",
+    "max_age_secs",
+    "min_size",
+    "max_size",
+    "default_size",
+    "background",
+    "palette",
+    "shape",
+    "mask",
+    "antialias",
+    "formats",
+];
+
+/// Settings for the service, from an optional TOML file overridden by
+/// `IDENTICON_<KEY>` environment variables. Keys are the field names, and
+/// `formats` takes a comma-separated list in the environment.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub host: IpAddr,
+    pub port: u16,
+    /// Memory budget for cached responses, in bytes.
+    pub cache_bytes: u64,
+    /// Longest name served, in bytes.
+    pub max_name_len: usize,
+    pub timeout_secs: u64,
+    /// `max-age` of the `Cache-Control` header.
+    pub max_age_secs: u64,
+    pub min_size: u32,
+    pub max_size: u32,
+    pub default_size: u32,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub background: Rgba<u8>,
+    #[serde(deserialize_with = "deserialize_some")]
+    pub palette: Option<Palette>,
+    #[serde(deserialize_with = "deserialize_some")]
+    pub shape: Option<CellShape>,
+    #[serde(deserialize_with = "deserialize_some")]
+    pub mask: Option<Mask>,
+    /// Smooths the edges of round and slanted shapes by default.
+    pub antialias: bool,
+    /// Formats that are served, in no particular order. Requests for any
+    /// other get a 404, and negotiation only picks among these.
+    #[serde(deserialize_with = "deserialize_formats")]
+    pub formats: Vec<Format>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            host: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: 8000,
+            cache_bytes: 64 * 1024 * 1024,
+            max_name_len: 256,
+            timeout_secs: 10,
+            max_age_secs: 30672000,
+            min_size: 16,
+            max_size: 1024,
+            default_size: crate::IMAGE_SIZE,
+            background: crate::BACKGROUND,
+            palette: None,
+            shape: None,
+            mask: None,
+            antialias: false,
+            formats: Format::ALL.to_vec(),
+        }
+    }
+}
+
+impl Config {
+    /// Reads the file named by `IDENTICON_CONFIG`, or `identicon.toml` if it
+    /// exists, applies the environment on top and validates the result.
+    pub fn load() -> Result<Config, String> {
+        let mut config = match env::var_os("IDENTICON_CONFIG") {
+            Some(path) => Config::from_file(path)?,
+            None if Path::new(DEFAULT_FILE).exists() => Config::from_file(DEFAULT_FILE)?,
+            None => Config::default(),
+        };
+        // other variables may hold anything, so only ours need to be UTF-8
+        for (key, value) in env::vars_os() {
+            let Some(name) = key.to_str().and_then(|key| key.strip_prefix("IDENTICON_")) else {
+                continue;
+            };
+            let name = name.to_ascii_lowercase();
+            if name == "config" {
+                continue;
+            }
+            if !KEYS.contains(&name.as_str()) {
+                warn!("ignoring unknown setting {}", key.to_string_lossy());
+                continue;
+            }
+            let value = value
+                .to_str()
+                .ok_or_else(|| format!("{}: not valid UTF-8", key.to_string_lossy()))?;
+            config
+                .set(&name, value)
+                .map_err(|err| format!("{}: {}", key.to_string_lossy(), err))?;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Config, String> {
+        let path = path.as_ref();
+        let text =
+            fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        toml::from_str(&text).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    /// Overrides the setting `key` with a value written as in the
+    /// environment.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        fn number<T: FromStr>(value: &str) -> Result<T, String> {
+            value
+                .parse()
+                .map_err(|_| format!("`{}` is not a valid number", value))
+        }
+        fn named<T: FromStr<Err = UnknownName>>(value: &str) -> Result<T, String> {
+            value.parse().map_err(|err: UnknownName| err.to_string())
+        }
+
+        match key {
+            "host" => {
+                self.host = value
+                    .parse()
+                    .map_err(|_| format!("`{}` is not an IP address", value))?
+            }
+            "port" => self.port = number(value)?,
+            "cache_bytes" => self.cache_bytes = number(value)?,
+            "max_name_len" => self.max_name_len = number(value)?,
+            "timeout_secs" => self.timeout_secs = number(value)?,
+            "max_age_secs" => self.max_age_secs = number(value)?,
+            "min_size" => self.min_size = number(value)?,
+            "max_size" => self.max_size = number(value)?,
+            "default_size" => self.default_size = number(value)?,
+            "background" => self.background = parse_color(value)?,
+            "palette" => self.palette = Some(named(value)?),
+            "shape" => self.shape = Some(named(value)?),
+            "mask" => self.mask = Some(named(value)?),
+            "antialias" => {
+                self.antialias = value
+                    .parse()
+                    .map_err(|_| format!("`{}` is not true or false", value))?
+            }
+            "formats" => self.formats = parse_formats(value.split(','))?,
+            _ => return Err("unknown setting".to_string()),
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        // below one pixel per cell of the default grid there's nothing to see
+        if self.min_size < crate::SPRITE_SIZE || self.min_size > self.max_size {
+            return Err(format!(
+                "min_size must be at least {} and at most max_size",
+                crate::SPRITE_SIZE
+            ));
+        }
+        if self.max_size > SIZE_LIMIT {
+            return Err(format!("max_size must be at most {}", SIZE_LIMIT));
+        }
+        if !(self.min_size..=self.max_size).contains(&self.default_size) {
+            return Err("default_size must be between min_size and max_size".to_string());
+        }
+        if self.cache_bytes == 0 {
+            return Err("cache_bytes must be at least 1".to_string());
+        }
+        if self.max_name_len == 0 {
+            return Err("max_name_len must be at least 1".to_string());
+        }
+        if self.timeout_secs == 0 {
+            return Err("timeout_secs must be at least 1".to_string());
+        }
+        if self.formats.is_empty() {
+            return Err("formats must not be empty".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        SocketAddr::new(self.host, self.port)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    /// What a request without query parameters renders.
+    pub fn image_options(&self) -> IdenticonOptions {
+        let mut options = IdenticonOptions::new()
+            .size(self.default_size)
+            .background(self.background)
+            .antialias(self.antialias);
+        if let Some(palette) = &self.palette {
+            options = options.palette(palette.clone());
+        }
+        if let Some(shape) = self.shape {
+            options = options.shape(shape);
+        }
+        if let Some(mask) = self.mask {
+            options = options.mask(mask);
+        }
+        options
+    }
+
+    pub fn is_enabled(&self, format: Format) -> bool {
+        self.formats.contains(&format)
+    }
+}
+
+pub(crate) fn parse_color(value: &str) -> Result<Rgba<u8>, String> {
+    utils::parse_color(value).ok_or_else(|| "must be `transparent` or a hex color".to_string())
+}
+
+fn parse_formats<'a>(values: impl IntoIterator<Item = &'a str>) -> Result<Vec<Format>, String> {
+    values
+        .into_iter()
+        .map(|ext| ext.trim().parse::<Format>().map_err(|err| err.to_string()))
+        .collect()
+}
+
+fn deserialize_color<'de, D: Deserializer<'de>>(de: D) -> Result<Rgba<u8>, D::Error> {
+    parse_color(&String::deserialize(de)?).map_err(de::Error::custom)
+}
+
+fn deserialize_some<'de, D, T>(de: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr<Err = UnknownName>,
+{
+    String::deserialize(de)?
+        .parse()
+        .map(Some)
+        .map_err(de::Error::custom)
+}
+
+fn deserialize_formats<'de, D: Deserializer<'de>>(de: D) -> Result<Vec<Format>, D::Error> {
+    let formats = Vec::<String>::deserialize(de)?;
+    parse_formats(formats.iter().map(String::as_str)).map_err(de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+    use crate::{CellShape, Format, Rgba};
+
+    #[test]
+    fn it_reads_toml_and_overrides() {
+        let mut config: Config = toml::from_str(
+            r#"
+            port = 8080
+            max_size = 512
+            background = "transparent"
+            shape = "circle"
+            formats = ["png", "svg"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.port, 8080);
+        assert_eq!(config.min_size, 16);
+        assert_eq!(config.background, Rgba([0, 0, 0, 0]));
+        assert_eq!(config.shape, Some(CellShape::Circle));
+        assert_eq!(config.formats, [Format::Png, Format::Svg]);
+        assert!(config.validate().is_ok());
+
+        config.set("formats", "webp, jpg").unwrap();
+        assert_eq!(config.formats, [Format::Webp, Format::Jpeg]);
+        config.set("antialias", "true").unwrap();
+        assert!(config.antialias);
+        assert!(config.set("antialias", "yes").is_err());
+        assert!(config.set("palette", "neon").is_err());
+        assert!(config.set("colour", "red").is_err());
+
+        assert!(toml::from_str::<Config>("shape = \"hexagon\"").is_err());
+        assert!(toml::from_str::<Config>("sizes = 1").is_err());
+    }
+
+    #[test]
+    fn it_validates_ranges() {
+        assert!(Config::default().validate().is_ok());
+
+        let mut config = Config {
+            default_size: 2048,
+            ..Config::default()
+        };
+        assert!(config.validate().is_err());
+        config.max_size = 4096;
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.formats.clear();
+        assert!(config.validate().is_err());
+    }
+}
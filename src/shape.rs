@@ -0,0 +1,198 @@
+use std::str::FromStr;
+
+use crate::symmetry::Transform;
+use crate::utils::UnknownName;
+
+/// What each painted cell is drawn as.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CellShape {
+    #[default]
+    Square,
+    /// A square with corners rounded by the given percentage of half a cell.
+    Rounded(u8),
+    Circle,
+    /// An isosceles triangle with its base on one side of the cell, pointing
+    /// in a direction picked from the hash.
+    Triangle,
+    Diamond,
+}
+
+impl CellShape {
+    /// Looks up a shape by its lowercase name; `rounded` gets a 50% radius.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "square" => Some(CellShape::Square),
+            "rounded" => Some(CellShape::Rounded(50)),
+            "circle" => Some(CellShape::Circle),
+            "triangle" => Some(CellShape::Triangle),
+            "diamond" => Some(CellShape::Diamond),
+            _ => None,
+        }
+    }
+
+    /// Whether the point `(x, y)`, in cell units from the top-left corner,
+    /// lies inside the shape.
+    #[cfg(feature = "image")]
+    pub(crate) fn contains(self, direction: Direction, x: f64, y: f64) -> bool {
+        if !(0.0..=1.0).contains(&x) || !(0.0..=1.0).contains(&y) {
+            return false;
+        }
+        let (dx, dy) = ((x - 0.5).abs(), (y - 0.5).abs());
+        match self {
+            CellShape::Square => true,
+            CellShape::Rounded(radius) => {
+                let r = radius.min(100) as f64 / 200.0;
+                let (cx, cy) = ((dx - (0.5 - r)).max(0.0), (dy - (0.5 - r)).max(0.0));
+                cx * cx + cy * cy <= r * r
+            }
+            CellShape::Circle => dx * dx + dy * dy <= 0.25,
+            CellShape::Diamond => dx + dy <= 0.5,
+            CellShape::Triangle => {
+                // turn the point so the triangle always points up
+                let (x, y) = match direction {
+                    Direction::Up => (x, y),
+                    Direction::Right => (y, 1.0 - x),
+                    Direction::Down => (1.0 - x, 1.0 - y),
+                    Direction::Left => (1.0 - y, x),
+                };
+                (x - 0.5).abs() <= y / 2.0
+            }
+        }
+    }
+}
+
+impl FromStr for CellShape {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CellShape::from_name(s).ok_or_else(|| UnknownName::new("shape", s))
+    }
+}
+
+/// Which way a [`CellShape::Triangle`] points.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Direction {
+    #[default]
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl Direction {
+    const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Right,
+        Direction::Down,
+        Direction::Left,
+    ];
+
+    pub(crate) fn from_nibble(nibble: u8) -> Self {
+        Self::ALL[(nibble % 4) as usize]
+    }
+
+    pub(crate) fn transform(self, transform: Transform) -> Self {
+        let quarter = self as u8;
+        let quarter = match transform {
+            Transform::Identity => quarter,
+            Transform::MirrorX => (4 - quarter) % 4,
+            Transform::MirrorY => (6 - quarter) % 4,
+            Transform::Rotate(turns) => (quarter + turns) % 4,
+        };
+        Self::ALL[quarter as usize]
+    }
+
+    /// Corners of the triangle pointing this way, in cell units.
+    pub(crate) fn triangle(self) -> [(f64, f64); 3] {
+        match self {
+            Direction::Up => [(0.5, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            Direction::Right => [(1.0, 0.5), (0.0, 1.0), (0.0, 0.0)],
+            Direction::Down => [(0.5, 1.0), (0.0, 0.0), (1.0, 0.0)],
+            Direction::Left => [(0.0, 0.5), (1.0, 0.0), (1.0, 1.0)],
+        }
+    }
+}
+
+/// The outline the whole image is clipped to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Mask {
+    #[default]
+    Square,
+    /// Corners rounded by the given percentage of half the image.
+    Rounded(u8),
+    Circle,
+    /// A superellipse, between a rounded square and a circle.
+    Squircle,
+}
+
+impl Mask {
+    /// Looks up a mask by its lowercase name; `rounded` gets a 20% radius.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "square" => Some(Mask::Square),
+            "rounded" => Some(Mask::Rounded(20)),
+            "circle" => Some(Mask::Circle),
+            "squircle" => Some(Mask::Squircle),
+            _ => None,
+        }
+    }
+
+    /// Whether the point `(x, y)`, in image units from the top-left corner,
+    /// stays visible.
+    #[cfg(feature = "image")]
+    pub(crate) fn contains(self, x: f64, y: f64) -> bool {
+        match self {
+            Mask::Square => CellShape::Square.contains(Direction::Up, x, y),
+            Mask::Rounded(radius) => CellShape::Rounded(radius).contains(Direction::Up, x, y),
+            Mask::Circle => CellShape::Circle.contains(Direction::Up, x, y),
+            Mask::Squircle => {
+                let (dx, dy) = (2.0 * x - 1.0, 2.0 * y - 1.0);
+                dx.powi(4) + dy.powi(4) <= 1.0
+            }
+        }
+    }
+
+    /// Points along the squircle outline, in image units.
+    pub(crate) fn squircle_outline(points: usize) -> Vec<(f64, f64)> {
+        (0..points)
+            .map(|i| {
+                let t = i as f64 / points as f64 * std::f64::consts::TAU;
+                let (sin, cos) = t.sin_cos();
+                // |x|^4 + |y|^4 = 1 parametrized through the square roots
+                let x = cos.signum() * cos.abs().sqrt();
+                let y = sin.signum() * sin.abs().sqrt();
+                (0.5 + x / 2.0, 0.5 + y / 2.0)
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Mask {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Mask::from_name(s).ok_or_else(|| UnknownName::new("mask", s))
+    }
+}
+
+#[cfg(all(test, feature = "image"))]
+mod tests {
+    use super::{CellShape, Direction};
+
+    #[test]
+    fn it_points_triangles() {
+        for direction in Direction::ALL {
+            let [apex, ..] = direction.triangle();
+            let shape = CellShape::Triangle;
+            assert!(shape.contains(direction, apex.0, apex.1));
+            assert!(shape.contains(direction, 1.0 - apex.0, 1.0 - apex.1));
+            assert!(shape.contains(direction, 0.5, 0.5));
+            for corner in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)] {
+                let beside_apex = (corner.0 - apex.0) * (corner.0 - apex.0)
+                    + (corner.1 - apex.1) * (corner.1 - apex.1)
+                    == 0.25;
+                assert_eq!(shape.contains(direction, corner.0, corner.1), !beside_apex);
+            }
+        }
+    }
+}
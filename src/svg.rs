@@ -0,0 +1,159 @@
+use std::fmt::Write;
+
+use crate::{CellShape, Identicon, IdenticonOptions, Mask, Rgb};
+
+pub(crate) fn render(identicon: &Identicon, options: &IdenticonOptions) -> String {
+    let size = options.size;
+    let shape = options.shape;
+    let layout = options.layout(identicon.grid);
+    let mut svg = String::with_capacity(512);
+    // crisp edges keep squares sharp, but would make curves and slopes jagged
+    let rendering = match shape {
+        CellShape::Square => r#" shape-rendering="crispEdges""#,
+        _ => "",
+    };
+    write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{size}" viewBox="0 0 {size} {size}"{rendering}>"#
+    )
+    .unwrap();
+    if options.mask != Mask::Square {
+        // identical masks share an id, so inlining several avatars is safe
+        let id = format!("identicon-mask-{}", size);
+        let id = match options.mask {
+            Mask::Rounded(radius) => format!("{id}-rounded-{radius}"),
+            Mask::Circle => format!("{id}-circle"),
+            _ => format!("{id}-squircle"),
+        };
+        write!(svg, r#"<clipPath id="{id}">"#).unwrap();
+        clip(&mut svg, options.mask, size).unwrap();
+        write!(svg, r#"</clipPath><g clip-path="url(#{id})">"#).unwrap();
+    }
+    let [r, g, b, alpha] = identicon.background.0;
+    match alpha {
+        0 => {}
+        255 => write!(
+            svg,
+            r#"<rect width="{size}" height="{size}" fill="{}"/>"#,
+            hex(Rgb([r, g, b]))
+        )
+        .unwrap(),
+        alpha => write!(
+            svg,
+            r#"<rect width="{size}" height="{size}" fill="{}" fill-opacity="{:.3}"/>"#,
+            hex(Rgb([r, g, b])),
+            alpha as f32 / 255.0
+        )
+        .unwrap(),
+    }
+    for (tone, color) in identicon.colors.iter().enumerate() {
+        let mut cells = layout
+            .cells(&identicon.pixels)
+            .filter(|(ix, _, _)| identicon.tones[*ix] as usize == tone)
+            .peekable();
+        if cells.peek().is_none() {
+            continue;
+        }
+        write!(svg, r#"<g fill="{}">"#, hex(*color)).unwrap();
+        for (ix, x, y) in cells {
+            cell(&mut svg, shape, identicon, ix, x, y, layout.cell);
+        }
+        svg.push_str("</g>");
+    }
+    if options.mask != Mask::Square {
+        svg.push_str("</g>");
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+fn clip(svg: &mut String, mask: Mask, size: u32) -> std::fmt::Result {
+    match mask {
+        Mask::Square => Ok(()),
+        Mask::Rounded(radius) => write!(
+            svg,
+            r#"<rect width="{size}" height="{size}" rx="{}"/>"#,
+            num(size as f64 * radius.min(100) as f64 / 200.0)
+        ),
+        Mask::Circle => {
+            let r = num(size as f64 / 2.0);
+            write!(svg, r#"<circle cx="{r}" cy="{r}" r="{r}"/>"#)
+        }
+        Mask::Squircle => polygon(svg, 0, 0, size, &Mask::squircle_outline(64)),
+    }
+}
+
+fn cell(
+    svg: &mut String,
+    shape: CellShape,
+    identicon: &Identicon,
+    ix: usize,
+    x: u32,
+    y: u32,
+    size: u32,
+) {
+    match shape {
+        CellShape::Square => write!(
+            svg,
+            r#"<rect x="{x}" y="{y}" width="{size}" height="{size}"/>"#
+        ),
+        CellShape::Rounded(radius) => write!(
+            svg,
+            r#"<rect x="{x}" y="{y}" width="{size}" height="{size}" rx="{}"/>"#,
+            num(size as f64 * radius.min(100) as f64 / 200.0)
+        ),
+        CellShape::Circle => {
+            let r = size as f64 / 2.0;
+            write!(
+                svg,
+                r#"<circle cx="{}" cy="{}" r="{}"/>"#,
+                num(x as f64 + r),
+                num(y as f64 + r),
+                num(r)
+            )
+        }
+        CellShape::Diamond => polygon(
+            svg,
+            x,
+            y,
+            size,
+            &[(0.5, 0.0), (1.0, 0.5), (0.5, 1.0), (0.0, 0.5)],
+        ),
+        CellShape::Triangle => polygon(svg, x, y, size, &identicon.directions[ix].triangle()),
+    }
+    .unwrap();
+}
+
+/// Writes a polygon from corners given in cell units.
+fn polygon(
+    svg: &mut String,
+    x: u32,
+    y: u32,
+    size: u32,
+    corners: &[(f64, f64)],
+) -> std::fmt::Result {
+    svg.push_str(r#"<polygon points=""#);
+    for (i, (px, py)) in corners.iter().enumerate() {
+        if i > 0 {
+            svg.push(' ');
+        }
+        write!(
+            svg,
+            "{},{}",
+            num(x as f64 + px * size as f64),
+            num(y as f64 + py * size as f64)
+        )?;
+    }
+    svg.push_str(r#""/>"#);
+    Ok(())
+}
+
+/// Formats a coordinate without a trailing `.0` or float noise.
+fn num(value: f64) -> String {
+    let value = (value * 100.0).round() / 100.0;
+    format!("{value}")
+}
+
+fn hex(Rgb([r, g, b]): Rgb<u8>) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
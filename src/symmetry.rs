@@ -0,0 +1,82 @@
+use std::str::FromStr;
+
+use crate::utils::UnknownName;
+
+/// How the cells seeded from the hash are repeated across the grid.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    /// Mirrored left to right across the center column.
+    #[default]
+    Horizontal,
+    /// Mirrored top to bottom across the center row.
+    Vertical,
+    /// Mirrored across both center lines, like a kaleidoscope.
+    FourWay,
+    /// Unchanged by a half turn around the center.
+    Rotational180,
+    /// Unchanged by a quarter turn around the center.
+    Rotational90,
+    /// Every cell seeded independently.
+    None,
+}
+
+impl Symmetry {
+    /// Looks up a symmetry by its lowercase, dash-separated name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "horizontal" => Some(Symmetry::Horizontal),
+            "vertical" => Some(Symmetry::Vertical),
+            "four-way" => Some(Symmetry::FourWay),
+            "rotational-180" => Some(Symmetry::Rotational180),
+            "rotational-90" => Some(Symmetry::Rotational90),
+            "none" => Some(Symmetry::None),
+            _ => None,
+        }
+    }
+
+    /// Every cell that must share a color with `(row, col)` in an `n` x `n`
+    /// grid, starting with `(row, col)` itself, and how the seed cell is
+    /// carried onto each of them.
+    pub(crate) fn orbit(self, n: usize, row: usize, col: usize) -> Vec<(usize, usize, Transform)> {
+        use Transform::*;
+
+        let (r, c) = (row, col);
+        let (fr, fc) = (n - 1 - row, n - 1 - col);
+        match self {
+            Symmetry::Horizontal => vec![(r, c, Identity), (r, fc, MirrorX)],
+            Symmetry::Vertical => vec![(r, c, Identity), (fr, c, MirrorY)],
+            Symmetry::FourWay => vec![
+                (r, c, Identity),
+                (r, fc, MirrorX),
+                (fr, c, MirrorY),
+                (fr, fc, Rotate(2)),
+            ],
+            Symmetry::Rotational180 => vec![(r, c, Identity), (fr, fc, Rotate(2))],
+            Symmetry::Rotational90 => vec![
+                (r, c, Identity),
+                (c, fr, Rotate(1)),
+                (fr, fc, Rotate(2)),
+                (fc, r, Rotate(3)),
+            ],
+            Symmetry::None => vec![(r, c, Identity)],
+        }
+    }
+}
+
+impl FromStr for Symmetry {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Symmetry::from_name(s).ok_or_else(|| UnknownName::new("symmetry", s))
+    }
+}
+
+/// A mirror or clockwise quarter turns mapping one cell of an orbit onto
+/// another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Transform {
+    Identity,
+    MirrorX,
+    MirrorY,
+    Rotate(u8),
+}
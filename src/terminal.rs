@@ -0,0 +1,174 @@
+use std::fmt::Write;
+use std::str::FromStr;
+
+use crate::utils::UnknownName;
+use crate::{Identicon, Rgb};
+
+/// How an identicon is drawn with text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TerminalMode {
+    /// Half blocks colored with 24-bit escapes.
+    #[default]
+    TrueColor,
+    /// Half blocks colored from the xterm 256-color palette.
+    Ansi256,
+    /// `#` for painted cells and spaces elsewhere, without any escapes.
+    Ascii,
+}
+
+impl TerminalMode {
+    pub fn from_name(name: &str) -> Option<TerminalMode> {
+        match name {
+            "truecolor" | "24bit" => Some(TerminalMode::TrueColor),
+            "256" | "ansi256" => Some(TerminalMode::Ansi256),
+            "ascii" => Some(TerminalMode::Ascii),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for TerminalMode {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TerminalMode::from_name(s).ok_or_else(|| UnknownName::new("terminal mode", s))
+    }
+}
+
+/// One line per text row. Colored modes pack two grid rows into each line
+/// with `▀`/`▄`, ASCII spends two columns per cell to stay roughly square.
+pub(crate) fn render(identicon: &Identicon, mode: TerminalMode) -> String {
+    let grid = identicon.grid;
+    let mut out = String::new();
+    if mode == TerminalMode::Ascii {
+        for row in 0..grid {
+            for col in 0..grid {
+                out += if identicon.is_painted(row, col) {
+                    "##"
+                } else {
+                    "  "
+                };
+            }
+            out.push('\n');
+        }
+        return out;
+    }
+
+    // a transparent background is left to the terminal's own
+    let background = identicon.background;
+    let background =
+        (background.0[3] == 255).then(|| Rgb([background.0[0], background.0[1], background.0[2]]));
+    let color = |row: u32, col: u32| {
+        if row < grid {
+            identicon.color_at(row, col).or(background)
+        } else {
+            None
+        }
+    };
+    for row in (0..grid).step_by(2) {
+        for col in 0..grid {
+            match (color(row, col), color(row + 1, col)) {
+                (None, None) => out.push(' '),
+                (Some(top), None) => {
+                    out += &escape(mode, 38, top);
+                    out.push('▀');
+                }
+                (None, Some(bottom)) => {
+                    out += &escape(mode, 38, bottom);
+                    out.push('▄');
+                }
+                (Some(top), Some(bottom)) => {
+                    out += &escape(mode, 38, top);
+                    out += &escape(mode, 48, bottom);
+                    out.push('▀');
+                }
+            }
+            out += "\x1b[0m";
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// SGR sequence setting the foreground (`38`) or background (`48`).
+fn escape(mode: TerminalMode, layer: u8, color: Rgb<u8>) -> String {
+    let [r, g, b] = color.0;
+    let mut sgr = String::new();
+    match mode {
+        TerminalMode::Ansi256 => write!(sgr, "\x1b[{};5;{}m", layer, xterm256(color)),
+        _ => write!(sgr, "\x1b[{};2;{};{};{}m", layer, r, g, b),
+    }
+    .unwrap();
+    sgr
+}
+
+/// Nearest entry in the 6x6x6 color cube or the 24-step gray ramp.
+fn xterm256(color: Rgb<u8>) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let nearest = |c: u8| {
+        (0..6)
+            .min_by_key(|&i| (LEVELS[i] as i32 - c as i32).abs())
+            .unwrap()
+    };
+    let distance = |other: [u8; 3]| -> i32 {
+        color
+            .0
+            .iter()
+            .zip(other)
+            .map(|(&a, b)| (a as i32 - b as i32).pow(2))
+            .sum()
+    };
+
+    let [r, g, b] = color.0.map(nearest);
+    let cube = [LEVELS[r], LEVELS[g], LEVELS[b]];
+    let mean = color.0.iter().map(|&c| c as u32).sum::<u32>() / 3;
+    let step = (mean.saturating_sub(3) / 10).min(23) as u8;
+    let gray = 8 + 10 * step;
+
+    if distance([gray; 3]) < distance(cube) {
+        232 + step
+    } else {
+        16 + 36 * r as u8 + 6 * g as u8 + b as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Rgb;
+
+    use super::{xterm256, TerminalMode};
+    use crate::IdenticonOptions;
+
+    #[test]
+    fn it_renders_ascii() {
+        let identicon = IdenticonOptions::new().identicon(b"abc");
+        let art = identicon.render_terminal(TerminalMode::Ascii);
+        let sprite: String = art
+            .lines()
+            .flat_map(|line| line.as_bytes().chunks(2))
+            .map(|cell| if cell == b"##" { '#' } else { '.' })
+            .collect();
+        assert_eq!(sprite, ".#.#.#.#.######......#.#.");
+    }
+
+    #[test]
+    fn it_renders_half_blocks() {
+        let identicon = IdenticonOptions::new().identicon(b"abc");
+        let art = identicon.render_terminal(TerminalMode::TrueColor);
+        assert_eq!(art.lines().count(), 3);
+        assert!(art.contains("\x1b[38;2;51;51;102m"));
+        // the last row has nothing below it
+        assert_eq!(art.lines().last().unwrap().matches('▀').count(), 5);
+
+        let art = identicon.render_terminal(TerminalMode::Ansi256);
+        assert!(art.contains("\x1b[38;5;238m"));
+    }
+
+    #[test]
+    fn it_maps_to_xterm256() {
+        assert_eq!(xterm256(Rgb([0, 0, 0])), 16);
+        assert_eq!(xterm256(Rgb([255, 255, 255])), 231);
+        assert_eq!(xterm256(Rgb([255, 0, 0])), 196);
+        assert_eq!(xterm256(Rgb([128, 128, 128])), 244);
+    }
+}
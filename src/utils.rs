@@ -1,4 +1,9 @@
+use std::fmt;
+
 use md5::{Digest, Md5};
+use sha2::Sha256;
+
+use crate::Rgba;
 
 pub fn md5(data: &[u8]) -> [u8; 16] {
     // https://github.com/rust-lang/rust-analyzer/issues/15242
@@ -6,3 +11,91 @@ pub fn md5(data: &[u8]) -> [u8; 16] {
     hasher.update(data);
     hasher.finalize().into()
 }
+
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = <Sha256 as Digest>::new();
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+pub fn blake3(data: &[u8]) -> [u8; 32] {
+    blake3::hash(data).into()
+}
+
+pub fn xxh3(data: &[u8]) -> [u8; 16] {
+    xxhash_rust::xxh3::xxh3_128(data).to_be_bytes()
+}
+
+/// Parses `transparent` or a hex color in `rgb`, `rrggbb` or `rrggbbaa` form,
+/// with or without a leading `#`.
+pub fn parse_color(s: &str) -> Option<Rgba<u8>> {
+    if s.eq_ignore_ascii_case("transparent") {
+        return Some(Rgba([0, 0, 0, 0]));
+    }
+
+    let s = s.strip_prefix('#').unwrap_or(s);
+    if !s.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize, len: usize| {
+        let value = u8::from_str_radix(&s[i * len..(i + 1) * len], 16).ok()?;
+        Some(if len == 1 { value * 0x11 } else { value })
+    };
+    match s.len() {
+        3 => Some(Rgba([channel(0, 1)?, channel(1, 1)?, channel(2, 1)?, 255])),
+        6 => Some(Rgba([channel(0, 2)?, channel(1, 2)?, channel(2, 2)?, 255])),
+        8 => Some(Rgba([
+            channel(0, 2)?,
+            channel(1, 2)?,
+            channel(2, 2)?,
+            channel(3, 2)?,
+        ])),
+        _ => None,
+    }
+}
+
+/// A name that doesn't match any palette, shape or other setting it was
+/// parsed as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownName {
+    pub kind: &'static str,
+    pub name: String,
+}
+
+impl UnknownName {
+    pub(crate) fn new(kind: &'static str, name: &str) -> Self {
+        UnknownName {
+            kind,
+            name: name.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for UnknownName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown {} `{}`", self.kind, self.name)
+    }
+}
+
+impl std::error::Error for UnknownName {}
+
+#[cfg(test)]
+mod tests {
+    use crate::Rgba;
+
+    use super::parse_color;
+
+    #[test]
+    fn it_parses_colors() {
+        assert_eq!(parse_color("transparent"), Some(Rgba([0, 0, 0, 0])));
+        assert_eq!(parse_color("fff"), Some(Rgba([255, 255, 255, 255])));
+        assert_eq!(parse_color("#1a2b3c"), Some(Rgba([0x1a, 0x2b, 0x3c, 255])));
+        assert_eq!(
+            parse_color("1a2b3c80"),
+            Some(Rgba([0x1a, 0x2b, 0x3c, 0x80]))
+        );
+        assert_eq!(parse_color("12345"), None);
+        assert_eq!(parse_color("ggg"), None);
+        assert_eq!(parse_color("ééé"), None);
+    }
+}
@@ -0,0 +1,145 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "image")]
+use crate::Format;
+use crate::{utils, CellShape, HashAlgorithm, IdenticonOptions, Mask, Palette, Symmetry};
+
+/// Identicon settings for JavaScript, set one at a time with the same names
+/// the CLI and server accept.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct Options(IdenticonOptions);
+
+#[wasm_bindgen]
+impl Options {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Options {
+        Options::default()
+    }
+
+    #[wasm_bindgen(js_name = setSize)]
+    pub fn set_size(&mut self, size: u32) -> Result<(), JsError> {
+        if size == 0 {
+            return Err(JsError::new("size must be at least 1"));
+        }
+        self.0 = self.0.clone().size(size);
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = setMargin)]
+    pub fn set_margin(&mut self, margin: u32) {
+        self.0 = self.0.clone().margin(margin);
+    }
+
+    #[wasm_bindgen(js_name = setGrid)]
+    pub fn set_grid(&mut self, grid: u32) -> Result<(), JsError> {
+        if grid == 0 {
+            return Err(JsError::new("grid must be at least 1"));
+        }
+        self.0 = self.0.clone().grid(grid);
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = setAntialias)]
+    pub fn set_antialias(&mut self, antialias: bool) {
+        self.0 = self.0.clone().antialias(antialias);
+    }
+
+    #[wasm_bindgen(js_name = setBackground)]
+    pub fn set_background(&mut self, color: &str) -> Result<(), JsError> {
+        let color = utils::parse_color(color)
+            .ok_or_else(|| JsError::new(&format!("unknown color `{}`", color)))?;
+        self.0 = self.0.clone().background(color);
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = setPalette)]
+    pub fn set_palette(&mut self, name: &str) -> Result<(), JsError> {
+        self.0 = self.0.clone().palette(name.parse::<Palette>()?);
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = setShape)]
+    pub fn set_shape(&mut self, name: &str) -> Result<(), JsError> {
+        self.0 = self.0.clone().shape(name.parse::<CellShape>()?);
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = setMask)]
+    pub fn set_mask(&mut self, name: &str) -> Result<(), JsError> {
+        self.0 = self.0.clone().mask(name.parse::<Mask>()?);
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = setSymmetry)]
+    pub fn set_symmetry(&mut self, name: &str) -> Result<(), JsError> {
+        self.0 = self.0.clone().symmetry(name.parse::<Symmetry>()?);
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = setHash)]
+    pub fn set_hash(&mut self, name: &str) -> Result<(), JsError> {
+        self.0 = self.0.clone().hash(name.parse::<HashAlgorithm>()?);
+        Ok(())
+    }
+
+    pub fn svg(&self, name: &str) -> String {
+        self.0.gen_svg(name.as_bytes())
+    }
+
+    /// PNG file contents, as served by the server.
+    #[cfg(feature = "image")]
+    pub fn png(&self, name: &str) -> Vec<u8> {
+        Format::Png.encode(&self.0, name.as_bytes())
+    }
+
+    pub fn grid(&self, name: &str) -> Grid {
+        let identicon = self.0.identicon(name.as_bytes());
+        let [r, g, b] = identicon.foreground().0;
+        Grid {
+            size: identicon.grid_size(),
+            pixels: identicon.pixels().iter().map(|&p| p as u8).collect(),
+            color: format!("#{:02x}{:02x}{:02x}", r, g, b),
+        }
+    }
+}
+
+/// The cells of an identicon, for drawing it yourself.
+#[wasm_bindgen]
+pub struct Grid {
+    size: u32,
+    pixels: Vec<u8>,
+    color: String,
+}
+
+#[wasm_bindgen]
+impl Grid {
+    /// Cells along each side.
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Row-major cells, `1` where the foreground is painted.
+    #[wasm_bindgen(getter)]
+    pub fn pixels(&self) -> Vec<u8> {
+        self.pixels.clone()
+    }
+
+    /// The foreground as `#rrggbb`.
+    #[wasm_bindgen(getter)]
+    pub fn color(&self) -> String {
+        self.color.clone()
+    }
+}
+
+#[wasm_bindgen(js_name = genSvg)]
+pub fn gen_svg(name: &str) -> String {
+    Options::new().svg(name)
+}
+
+#[cfg(feature = "image")]
+#[wasm_bindgen(js_name = genPng)]
+pub fn gen_png(name: &str) -> Vec<u8> {
+    Options::new().png(name)
+}